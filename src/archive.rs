@@ -0,0 +1,236 @@
+//! Release archives of revisions, built by `jrny package` so that exactly the
+//! revisions that were packaged can be applied later, eg. by a deploy pipeline.
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::context::ChecksumAlgorithm;
+use crate::revisions::RevisionFile;
+use crate::{Error, Result};
+
+/// The manifest of checksums at the root of the archive
+const MANIFEST: &str = "manifest.toml";
+
+/// The directory within the archive holding the revision files
+const REVISIONS: &str = "revisions/";
+
+/// A file of the archive, as recorded in its manifest.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct ManifestEntry {
+    filename: String,
+    /// The SHA-256 checksum of the contents
+    checksum: String,
+}
+
+#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+struct Manifest {
+    #[serde(default, rename = "file")]
+    files: Vec<ManifestEntry>,
+}
+
+/// The revision files of an archive, whose contents have been verified against its manifest.
+#[derive(Debug)]
+pub(crate) struct ArchiveTree {
+    /// The revisions directory that the files stand in for
+    dir: PathBuf,
+    /// The contents of each file by filename
+    files: BTreeMap<String, String>,
+}
+
+impl ArchiveTree {
+    /// Reads the archive, failing if any file does not match its checksum in the manifest
+    /// or if the archive and manifest do not have exactly the same files.
+    pub(crate) fn open(archive: &Path, dir: &Path) -> Result<Self> {
+        let pathstr = archive.display().to_string();
+        let invalid = |reason: String| Error::ArchiveInvalid(pathstr.clone(), reason);
+
+        let mut manifest = None;
+        let mut files = BTreeMap::new();
+        let mut tar = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+
+            // Archives repacked by hand can have entries for directories
+            if entry.header().entry_type().is_dir() {
+                continue;
+            }
+
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut contents = String::new();
+
+            entry.read_to_string(&mut contents)?;
+
+            if path == MANIFEST {
+                manifest = Some(
+                    toml::from_str::<Manifest>(&contents)
+                        .map_err(|e| invalid(format!("the manifest is invalid - {}", e)))?,
+                );
+            } else if let Some(filename) = path.strip_prefix(REVISIONS) {
+                files.insert(filename.to_string(), contents);
+            } else {
+                return Err(invalid(format!("`{}` is not a revision", path)));
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| invalid("the manifest is missing".to_string()))?;
+        let algorithm = ChecksumAlgorithm::default();
+
+        for entry in &manifest.files {
+            match files.get(&entry.filename) {
+                Some(contents) if algorithm.checksum(contents) == entry.checksum => {}
+                Some(_) => {
+                    return Err(invalid(format!(
+                        "`{}` does not match its checksum",
+                        entry.filename
+                    )))
+                }
+                None => return Err(invalid(format!("`{}` is missing", entry.filename))),
+            }
+        }
+
+        let listed: HashSet<&str> = manifest
+            .files
+            .iter()
+            .map(|entry| entry.filename.as_str())
+            .collect();
+
+        if let Some(unlisted) = files
+            .keys()
+            .find(|filename| !listed.contains(filename.as_str()))
+        {
+            return Err(invalid(format!("`{}` is not in the manifest", unlisted)));
+        }
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            files,
+        })
+    }
+
+    /// The paths of the files, as though they were within the revisions directory.
+    pub(crate) fn files(&self) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .map(|filename| self.dir.join(filename))
+            .collect()
+    }
+
+    pub(crate) fn read(&self, p: &Path) -> Option<&str> {
+        let filename = p.strip_prefix(&self.dir).ok()?.to_str()?;
+
+        self.files.get(filename).map(String::as_str)
+    }
+}
+
+/// Writes an archive of the revisions and their down files to the path. Files hold
+/// the contents that checksums are computed from, ie. with any includes expanded and
+/// decompressed, so that they can be read without anything outside the archive.
+pub(crate) fn write(path: &Path, revisions: &[RevisionFile]) -> Result<()> {
+    let algorithm = ChecksumAlgorithm::default();
+    let mut manifest = Manifest::default();
+    let mut tar = tar::Builder::new(GzEncoder::new(
+        fs::File::create(path)?,
+        Compression::default(),
+    ));
+
+    let files = revisions.iter().flat_map(|revision| {
+        [(&revision.filename, &revision.contents)]
+            .into_iter()
+            .chain(
+                revision
+                    .down
+                    .iter()
+                    .map(|down| (&down.filename, &down.contents)),
+            )
+    });
+
+    for (filename, contents) in files {
+        append(&mut tar, &format!("{}{}", REVISIONS, filename), contents)?;
+        manifest.files.push(ManifestEntry {
+            filename: filename.clone(),
+            checksum: algorithm.checksum(contents),
+        });
+    }
+
+    let manifest = toml::to_string(&manifest).expect("manifest should serialize");
+
+    append(&mut tar, MANIFEST, &manifest)?;
+    tar.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+fn append<W: std::io::Write>(tar: &mut tar::Builder<W>, path: &str, contents: &str) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, path, contents.as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revisions::revision_file;
+
+    #[test]
+    fn archive_round_trips_and_detects_tampering() {
+        let dir = std::env::temp_dir().join(format!("jrny-archive-{}", std::process::id()));
+        let archive = dir.join("revisions.tar.gz");
+        let contents = "create table users (id int);\n";
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut revision = revision_file(1, "users", contents);
+        revision.filename.push_str(".gz");
+
+        write(&archive, &[revision]).unwrap();
+
+        let tree = ArchiveTree::open(&archive, Path::new("revisions")).unwrap();
+        let path = Path::new("revisions/001.1577836800.users.sql.gz");
+
+        assert_eq!(tree.files(), vec![path.to_owned()]);
+        assert_eq!(tree.read(path), Some(contents));
+
+        // Replace the revision with different contents under the same manifest
+        let mut tampered = tar::Builder::new(GzEncoder::new(
+            fs::File::create(dir.join("tampered.tar.gz")).unwrap(),
+            Compression::default(),
+        ));
+        let mut original = tar::Archive::new(GzDecoder::new(fs::File::open(&archive).unwrap()));
+
+        for entry in original.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut contents = String::new();
+
+            entry.read_to_string(&mut contents).unwrap();
+            if path != MANIFEST {
+                contents = "drop table users;\n".to_string();
+            }
+            append(&mut tampered, &path, &contents).unwrap();
+        }
+        tampered.into_inner().unwrap().finish().unwrap();
+
+        let result = ArchiveTree::open(&dir.join("tampered.tar.gz"), Path::new("revisions"));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        match result {
+            Err(Error::ArchiveInvalid(_, reason)) => {
+                assert!(reason.contains("does not match"), "received {}", reason);
+            }
+            result => panic!("received {:?}", result),
+        }
+    }
+}
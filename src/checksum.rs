@@ -0,0 +1,165 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::sql;
+use crate::Error;
+
+/// The normalizations applied to revision contents before hashing them, so that
+/// changes that do not affect the SQL itself do not count as changing the revision.
+///
+/// Each algorithm has a name that is recorded alongside the checksum, eg. `sha256` for
+/// the raw contents or `sha256+eol+ws` for normalized line endings and trailing whitespace,
+/// so that the checksum of an applied revision can always be reproduced even if the
+/// configured normalizations later change.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChecksumAlgorithm {
+    /// Whether to convert `\r\n` and `\r` line endings to `\n`
+    pub line_endings: bool,
+    /// Whether to remove whitespace from the end of each line and of the contents
+    pub trailing_whitespace: bool,
+    /// Whether to remove `--` and `/* */` comments
+    pub comments: bool,
+}
+
+const BASE_NAME: &str = "sha256";
+const LINE_ENDINGS: &str = "eol";
+const TRAILING_WHITESPACE: &str = "ws";
+const COMMENTS: &str = "comments";
+
+impl ChecksumAlgorithm {
+    /// Computes the hex-encoded SHA-256 hash of the normalized contents.
+    pub fn checksum(&self, contents: &str) -> String {
+        let mut normalized = contents.to_string();
+
+        if self.line_endings {
+            normalized = normalized.replace("\r\n", "\n").replace('\r', "\n");
+        }
+        if self.comments {
+            normalized = sql::strip_comments(&normalized);
+        }
+        if self.trailing_whitespace {
+            normalized = normalized
+                .split('\n')
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_end()
+                .to_string();
+        }
+
+        // See: https://users.rust-lang.org/t/sha256-result-to-string/49391/3
+        format!("{:x}", Sha256::digest(normalized.as_bytes()))
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", BASE_NAME)?;
+
+        for (enabled, suffix) in [
+            (self.line_endings, LINE_ENDINGS),
+            (self.trailing_whitespace, TRAILING_WHITESPACE),
+            (self.comments, COMMENTS),
+        ] {
+            if enabled {
+                write!(f, "+{}", suffix)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let mut parts = name.split('+');
+        let mut algorithm = Self::default();
+
+        if parts.next() != Some(BASE_NAME) {
+            return Err(Error::ChecksumAlgorithmUnknown(name.to_string()));
+        }
+
+        for part in parts {
+            match part {
+                LINE_ENDINGS => algorithm.line_endings = true,
+                TRAILING_WHITESPACE => algorithm.trailing_whitespace = true,
+                COMMENTS => algorithm.comments = true,
+                _ => return Err(Error::ChecksumAlgorithmUnknown(name.to_string())),
+            }
+        }
+
+        Ok(algorithm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithm_names_round_trip() {
+        for name in [
+            "sha256",
+            "sha256+eol",
+            "sha256+eol+ws+comments",
+            "sha256+ws",
+        ] {
+            let algorithm: ChecksumAlgorithm = name.parse().unwrap();
+
+            assert_eq!(algorithm.to_string(), name);
+        }
+
+        assert!("md5".parse::<ChecksumAlgorithm>().is_err());
+        assert!("sha256+tabs".parse::<ChecksumAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn raw_checksum_hashes_contents_as_is() {
+        assert_eq!(
+            ChecksumAlgorithm::default().checksum("select 1;\n"),
+            "4a45092ccf992ea92250053a80b931b787924ba61648f420555511b84f10ab6c"
+        );
+    }
+
+    #[test]
+    fn normalized_checksum_ignores_line_endings_and_whitespace() {
+        let algorithm = ChecksumAlgorithm {
+            line_endings: true,
+            trailing_whitespace: true,
+            comments: false,
+        };
+
+        assert_eq!(
+            algorithm.checksum("begin;  \r\nselect 1;\r\ncommit;\r\n\r\n"),
+            algorithm.checksum("begin;\nselect 1;\ncommit;")
+        );
+        assert_ne!(
+            algorithm.checksum("begin;\n-- note\ncommit;"),
+            algorithm.checksum("begin;\ncommit;")
+        );
+    }
+
+    #[test]
+    fn normalized_checksum_can_ignore_comments() {
+        let algorithm = ChecksumAlgorithm {
+            line_endings: false,
+            trailing_whitespace: true,
+            comments: true,
+        };
+
+        assert_eq!(
+            algorithm.checksum("select 1; -- note\n/* more */"),
+            algorithm.checksum("select 1;")
+        );
+        assert_ne!(
+            algorithm.checksum("select '-- not a comment';"),
+            algorithm.checksum("select '';")
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::context::{Config, Environment};
+use crate::db::client::libpq_vars;
+use crate::db::executor::{tracking_sequences, tracking_tables};
+use crate::revisions::BaselinedRevision;
+use crate::{Error, Result};
+
+/// The subdirectory of the revisions directory that baselined revisions are moved to.
+pub(super) const ARCHIVE_DIR: &str = "archive";
+
+/// Dumps the schema of the database specified by the environment using `pg_dump`,
+/// excluding the tables that track applied revisions, and adapts it to be run
+/// as a revision.
+pub(super) fn dump_schema(cfg: &Config, env: &Environment) -> Result<String> {
+    let output = Command::new("pg_dump")
+        .args(dump_args(cfg))
+        // Connecting through the environment keeps the password off the command line
+        .envs(libpq_vars(env)?)
+        .output()
+        .map_err(|e| Error::BaselineDumpFailed(format!("could not run `pg_dump` - {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::BaselineDumpFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(adapt_dump(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The arguments to `pg_dump`, which leave out the tracking tables and their sequences
+/// since they are created before any revision is run.
+fn dump_args(cfg: &Config) -> Vec<String> {
+    let mut args = vec!["--schema-only".to_string(), "--no-owner".to_string()];
+
+    for table in tracking_tables(cfg)
+        .into_iter()
+        .chain(tracking_sequences(cfg))
+    {
+        args.push(format!("--exclude-table={}.{}", cfg.table.schema, table));
+    }
+
+    args
+}
+
+/// Adapts `pg_dump` output so that it can be run as a revision within a transaction,
+/// alongside other revisions using the same connection:
+///
+/// - psql meta-commands (eg. `\connect`) are removed
+/// - session settings are made local to the transaction
+/// - schemas are only created if they do not exist, since the schema holding the
+///   revisions table will have already been created
+fn adapt_dump(dump: &str) -> String {
+    let mut sql = String::with_capacity(dump.len());
+
+    for line in dump.lines() {
+        if line.starts_with('\\') {
+            continue;
+        }
+
+        let line = if let Some(setting) = line.strip_prefix("SET ") {
+            format!("SET LOCAL {}", setting)
+        } else if let Some(schema) = line.strip_prefix("CREATE SCHEMA ") {
+            format!("CREATE SCHEMA IF NOT EXISTS {}", schema)
+        } else {
+            line.replace(
+                "set_config('search_path', '', false)",
+                "set_config('search_path', '', true)",
+            )
+        };
+
+        sql.push_str(&line);
+        sql.push('\n');
+    }
+
+    sql
+}
+
+/// The contents of a baseline revision standing in for the given revisions.
+pub(super) fn baseline_contents(
+    through_id: i64,
+    baselined: &[BaselinedRevision],
+    schema: &str,
+) -> String {
+    let directives: Vec<String> = baselined.iter().map(|b| b.to_directive()).collect();
+
+    format!(
+        "-- Revision: baseline
+--
+-- The schema as of revision {through_id}, standing in for the revisions below
+-- (moved to `{ARCHIVE_DIR}/`). Databases that applied all of them treat this
+-- baseline as applied, and only new databases will run it.
+--
+{directives}
+
+begin;
+
+{schema}
+commit;
+",
+        directives = directives.join("\n"),
+    )
+}
+
+/// Moves the given files into the archive directory within the revisions directory,
+/// failing before moving any if one would overwrite an existing file.
+pub(super) fn archive(revisions_dir: &Path, paths: &[&PathBuf]) -> Result<PathBuf> {
+    let archive_dir = revisions_dir.join(ARCHIVE_DIR);
+    let mut moves = Vec::new();
+
+    for path in paths {
+        let archived = archive_dir.join(path.file_name().unwrap());
+
+        if archived.exists() {
+            return Err(Error::PathAlreadyExists(archived.display().to_string()));
+        }
+        moves.push((path, archived));
+    }
+
+    fs::create_dir_all(&archive_dir)?;
+
+    for (path, archived) in moves {
+        fs::rename(path, archived)?;
+    }
+
+    Ok(archive_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_args_exclude_every_tracking_table() {
+        let cfg: Config = toml::from_str(
+            "[revisions]\ndirectory = \"revisions\"\n[table]\nschema = \"jrny\"\nname = \"revision\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            dump_args(&cfg),
+            vec![
+                "--schema-only",
+                "--no-owner",
+                "--exclude-table=jrny.revision",
+                "--exclude-table=jrny.revision_repairs",
+                "--exclude-table=jrny.revision_head",
+                "--exclude-table=jrny.revision_repairs_id_seq",
+            ]
+        );
+    }
+
+    #[test]
+    fn adapt_dump_keeps_settings_local() {
+        let dump = "\\restrict abc
+SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+CREATE SCHEMA app;
+CREATE TABLE app.users (id integer);
+\\unrestrict abc
+";
+
+        assert_eq!(
+            adapt_dump(dump),
+            "SET LOCAL statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', true);
+CREATE SCHEMA IF NOT EXISTS app;
+CREATE TABLE app.users (id integer);
+"
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::BTreeSet;
+
+use crate::db::catalog::{Catalog, Column, Grant};
+
+/// The statements that change the schema of the `from` catalog into that of the `to`
+/// catalog, ordered so that objects are dropped before those they depend on and
+/// created after them.
+///
+/// Changed views, constraints, and indexes are dropped and recreated, while changed
+/// functions are replaced and changed columns are altered in place where possible.
+pub(super) fn statements(from: &Catalog, to: &Catalog) -> Vec<String> {
+    let mut stmts = Vec::new();
+
+    let changed_views: BTreeSet<&str> = from
+        .views
+        .iter()
+        .filter(|view| to.view(&view.name).is_some_and(|new| new != *view))
+        .map(|view| view.name.as_str())
+        .collect();
+
+    // Relations that exist in both and are not recreated keep their indexes and grants
+    let kept = |name: &str| {
+        (from.tables.contains_key(name) && to.tables.contains_key(name))
+            || (from.view(name).is_some()
+                && to.view(name).is_some()
+                && !changed_views.contains(name))
+    };
+
+    let grant_kept = |grant: &Grant| match grant.kind.as_str() {
+        "TABLE" => kept(&grant.object),
+        "SCHEMA" => has_schema(from, &grant.object) && has_schema(to, &grant.object),
+        _ => [from, to]
+            .iter()
+            .all(|catalog| catalog.functions.contains_key(&grant.object)),
+    };
+
+    for grant in from.grants.difference(&to.grants).filter(|g| grant_kept(g)) {
+        stmts.push(format!(
+            "REVOKE {} ON {} {} FROM {};",
+            grant.privilege, grant.kind, grant.object, grant.grantee
+        ));
+    }
+
+    for view in from.views.iter().rev() {
+        if to.view(&view.name) != Some(view) {
+            stmts.push(format!(
+                "DROP {} {};",
+                view_kind(view.materialized),
+                view.name
+            ));
+        }
+    }
+
+    // Foreign keys are dropped first, since they can depend on other constraints, and
+    // even along with their tables, since they would block dropping referenced tables
+    for foreign_key in [true, false] {
+        for (key, constraint) in &from.constraints {
+            if constraint.foreign_key == foreign_key
+                && (foreign_key || to.tables.contains_key(&constraint.table))
+                && to.constraints.get(key) != Some(constraint)
+            {
+                stmts.push(format!(
+                    "ALTER TABLE {} DROP CONSTRAINT {};",
+                    constraint.table, key.1
+                ));
+            }
+        }
+    }
+
+    for (name, index) in &from.indexes {
+        if kept(&index.table) && to.indexes.get(name) != Some(index) {
+            stmts.push(format!("DROP INDEX {};", name));
+        }
+    }
+
+    for table in from.tables.keys() {
+        if !to.tables.contains_key(table) {
+            stmts.push(format!("DROP TABLE {};", table));
+        }
+    }
+
+    for (signature, function) in &from.functions {
+        if to.functions.get(signature).map(|f| f.procedure) != Some(function.procedure) {
+            stmts.push(format!(
+                "DROP {} {};",
+                function_kind(function.procedure),
+                signature
+            ));
+        }
+    }
+
+    for schema in to.schemas.difference(&from.schemas) {
+        stmts.push(format!("CREATE SCHEMA {};", schema));
+    }
+
+    for (signature, function) in &to.functions {
+        if from.functions.get(signature) != Some(function) {
+            stmts.push(format!("{};", function.definition.trim_end()));
+        }
+    }
+
+    for (table, columns) in &to.tables {
+        match from.tables.get(table) {
+            Some(old_columns) => stmts.extend(alter_columns(table, old_columns, columns)),
+            None => stmts.push(create_table(table, columns)),
+        }
+    }
+
+    // Foreign keys are added last, since they can depend on other constraints
+    for foreign_key in [false, true] {
+        for (key, constraint) in &to.constraints {
+            if constraint.foreign_key == foreign_key
+                && from.constraints.get(key) != Some(constraint)
+            {
+                stmts.push(format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} {};",
+                    constraint.table, key.1, constraint.definition
+                ));
+            }
+        }
+    }
+
+    for view in &to.views {
+        if from.view(&view.name) != Some(view) {
+            stmts.push(format!(
+                "CREATE {} {} AS\n{}",
+                view_kind(view.materialized),
+                view.name,
+                terminated(&view.definition)
+            ));
+        }
+    }
+
+    for (name, index) in &to.indexes {
+        if !kept(&index.table) || from.indexes.get(name) != Some(index) {
+            stmts.push(format!("{};", index.definition));
+        }
+    }
+
+    for grant in &to.grants {
+        if !grant_kept(grant) || !from.grants.contains(grant) {
+            stmts.push(grant_statement(grant));
+        }
+    }
+
+    // New functions can be executed by anyone unless that is revoked
+    for (signature, function) in &to.functions {
+        let public_execute = Grant {
+            kind: function_kind(function.procedure).to_string(),
+            object: signature.clone(),
+            grantee: "PUBLIC".to_string(),
+            privilege: "EXECUTE".to_string(),
+        };
+
+        if !grant_kept(&public_execute) && !to.grants.contains(&public_execute) {
+            stmts.push(format!(
+                "REVOKE EXECUTE ON {} {} FROM PUBLIC;",
+                public_execute.kind, signature
+            ));
+        }
+    }
+
+    for schema in from.schemas.difference(&to.schemas) {
+        stmts.push(format!("DROP SCHEMA {};", schema));
+    }
+
+    stmts
+}
+
+/// The contents of a revision running the given statements within a transaction.
+pub(super) fn diff_contents(name: &str, statements: &[String]) -> String {
+    format!(
+        "-- Revision: {name}
+--
+-- Generated by `jrny plan --diff`, which does not detect renames (instead dropping
+-- and recreating) or changes to objects like sequences, types, and triggers,
+-- so review carefully before applying.
+
+begin;
+
+{statements}
+
+commit;
+",
+        statements = statements.join("\n\n"),
+    )
+}
+
+/// Whether the catalog has the given schema, which `public` always is.
+fn has_schema(catalog: &Catalog, schema: &str) -> bool {
+    schema == "public" || catalog.schemas.contains(schema)
+}
+
+pub(super) fn create_table(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|column| format!("    {}", column_definition(column)))
+        .collect();
+
+    format!("CREATE TABLE {} (\n{}\n);", table, columns.join(",\n"))
+}
+
+/// The statements that change the columns of an existing table.
+fn alter_columns(table: &str, old_columns: &[Column], columns: &[Column]) -> Vec<String> {
+    let mut stmts = Vec::new();
+    let alter = |action: String| format!("ALTER TABLE {} {};", table, action);
+
+    for old in old_columns {
+        if !columns.iter().any(|column| column.name == old.name) {
+            stmts.push(alter(format!("DROP COLUMN {}", old.name)));
+        }
+    }
+
+    for column in columns {
+        let old = match old_columns.iter().find(|old| old.name == column.name) {
+            Some(old) => old,
+            None => {
+                stmts.push(alter(format!("ADD COLUMN {}", column_definition(column))));
+                continue;
+            }
+        };
+
+        // How values are generated cannot simply be altered, so the column is replaced
+        if old.identity != column.identity
+            || old.generated != column.generated
+            || old.serial != column.serial
+            || (column.generated && old.default != column.default)
+        {
+            stmts.push(alter(format!("DROP COLUMN {}", old.name)));
+            stmts.push(alter(format!("ADD COLUMN {}", column_definition(column))));
+            continue;
+        }
+
+        if old.data_type != column.data_type {
+            stmts.push(alter(format!(
+                "ALTER COLUMN {} TYPE {}",
+                column.name, column.data_type
+            )));
+        }
+
+        // The defaults of serial columns are tied to their sequences
+        if old.default != column.default && !column.serial && !column.generated {
+            stmts.push(alter(match &column.default {
+                Some(default) => format!("ALTER COLUMN {} SET DEFAULT {}", column.name, default),
+                None => format!("ALTER COLUMN {} DROP DEFAULT", column.name),
+            }));
+        }
+
+        if old.not_null != column.not_null {
+            stmts.push(alter(format!(
+                "ALTER COLUMN {} {} NOT NULL",
+                column.name,
+                if column.not_null { "SET" } else { "DROP" }
+            )));
+        }
+    }
+
+    stmts
+}
+
+fn column_definition(column: &Column) -> String {
+    let serial_type = match column.data_type.as_str() {
+        "smallint" => "smallserial",
+        "integer" => "serial",
+        "bigint" => "bigserial",
+        _ => column.data_type.as_str(),
+    };
+
+    let mut definition = format!(
+        "{} {}",
+        column.name,
+        if column.serial {
+            serial_type
+        } else {
+            &column.data_type
+        }
+    );
+
+    if let Some(identity) = &column.identity {
+        definition.push_str(&format!(" GENERATED {} AS IDENTITY", identity));
+    } else if let Some(default) = &column.default {
+        if column.generated {
+            definition.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", default));
+        } else if !column.serial {
+            definition.push_str(&format!(" DEFAULT {}", default));
+        }
+    }
+
+    if column.not_null {
+        definition.push_str(" NOT NULL");
+    }
+
+    definition
+}
+
+pub(super) fn grant_statement(grant: &Grant) -> String {
+    format!(
+        "GRANT {} ON {} {} TO {};",
+        grant.privilege, grant.kind, grant.object, grant.grantee
+    )
+}
+
+pub(super) fn view_kind(materialized: bool) -> &'static str {
+    if materialized {
+        "MATERIALIZED VIEW"
+    } else {
+        "VIEW"
+    }
+}
+
+fn function_kind(procedure: bool) -> &'static str {
+    if procedure {
+        "PROCEDURE"
+    } else {
+        "FUNCTION"
+    }
+}
+
+/// The statement with a terminating semicolon, which view definitions already have.
+pub(super) fn terminated(statement: &str) -> String {
+    let statement = statement.trim();
+
+    match statement.ends_with(';') {
+        true => statement.to_string(),
+        false => format!("{};", statement),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::{Constraint, View};
+
+    fn column(name: &str, data_type: &str) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            not_null: false,
+            default: None,
+            identity: None,
+            generated: false,
+            serial: false,
+        }
+    }
+
+    #[test]
+    fn statements_create_new_objects_in_dependency_order() {
+        let from = Catalog::default();
+        let mut to = Catalog::default();
+
+        to.tables.insert(
+            "public.users".to_string(),
+            vec![
+                Column {
+                    serial: true,
+                    not_null: true,
+                    default: Some("nextval('users_id_seq'::regclass)".to_string()),
+                    ..column("id", "integer")
+                },
+                Column {
+                    default: Some("'anonymous'::text".to_string()),
+                    ..column("name", "text")
+                },
+            ],
+        );
+        to.constraints.insert(
+            ("public.users".to_string(), "users_pkey".to_string()),
+            Constraint {
+                table: "public.users".to_string(),
+                foreign_key: false,
+                definition: "PRIMARY KEY (id)".to_string(),
+            },
+        );
+        to.views.push(View {
+            name: "public.names".to_string(),
+            materialized: false,
+            definition: " SELECT users.name\n   FROM users;".to_string(),
+        });
+        to.grants.insert(Grant {
+            kind: "TABLE".to_string(),
+            object: "public.names".to_string(),
+            grantee: "PUBLIC".to_string(),
+            privilege: "SELECT".to_string(),
+        });
+
+        assert_eq!(
+            statements(&from, &to),
+            vec![
+                "CREATE TABLE public.users (\n    id serial NOT NULL,\n    name text DEFAULT 'anonymous'::text\n);",
+                "ALTER TABLE public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);",
+                "CREATE VIEW public.names AS\nSELECT users.name\n   FROM users;",
+                "GRANT SELECT ON TABLE public.names TO PUBLIC;",
+            ]
+        );
+    }
+
+    #[test]
+    fn statements_alter_changed_columns_and_recreate_changed_views() {
+        let mut from = Catalog::default();
+        let view = View {
+            name: "public.names".to_string(),
+            materialized: false,
+            definition: " SELECT users.name\n   FROM users;".to_string(),
+        };
+        let grant = Grant {
+            kind: "TABLE".to_string(),
+            object: "public.names".to_string(),
+            grantee: "reader".to_string(),
+            privilege: "SELECT".to_string(),
+        };
+
+        from.tables.insert(
+            "public.users".to_string(),
+            vec![column("name", "varchar(20)"), column("age", "integer")],
+        );
+        from.views.push(view.clone());
+        from.grants.insert(grant.clone());
+
+        let mut to = from.clone();
+
+        to.tables.insert(
+            "public.users".to_string(),
+            vec![
+                Column {
+                    not_null: true,
+                    ..column("name", "text")
+                },
+                column("email", "text"),
+            ],
+        );
+        to.views[0].definition = " SELECT users.name, users.email\n   FROM users;".to_string();
+
+        assert_eq!(
+            statements(&from, &to),
+            vec![
+                "DROP VIEW public.names;",
+                "ALTER TABLE public.users DROP COLUMN age;",
+                "ALTER TABLE public.users ALTER COLUMN name TYPE text;",
+                "ALTER TABLE public.users ALTER COLUMN name SET NOT NULL;",
+                "ALTER TABLE public.users ADD COLUMN email text;",
+                "CREATE VIEW public.names AS\nSELECT users.name, users.email\n   FROM users;",
+                "GRANT SELECT ON TABLE public.names TO reader;",
+            ]
+        );
+    }
+
+    #[test]
+    fn statements_are_empty_for_identical_catalogs() {
+        let mut catalog = Catalog::default();
+
+        catalog
+            .tables
+            .insert("public.users".to_string(), vec![column("id", "integer")]);
+
+        assert!(statements(&catalog, &catalog.clone()).is_empty());
+    }
+}
@@ -1,19 +1,36 @@
+use std::env;
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::time::Instant;
 
 use chrono::{DateTime, Local, Utc};
 use log::{info, warn};
 
-use crate::context::{Config, Environment};
-use crate::revisions::RevisionFile;
+use crate::archive;
+use crate::context::{Config, Environment, LintLevel, SnapshotSettings};
+use crate::db::catalog::Catalog;
+use crate::db::client::Client;
+use crate::db::executor::tracking_tables;
+use crate::lockfile::Lockfile;
+use crate::revisions::{self, filename_stem, BaselinedRevision, RevisionFile};
+use crate::signature::{self, SignedManifest};
+use crate::sql;
 use crate::{Error, Executor, Result};
 
+mod baseline;
 mod begin;
+mod diff;
+mod renumber;
+mod repair;
 mod review;
+mod snapshot;
+mod template;
 
 use begin::Begin;
 use review::Review;
+use template::TemplateValues;
 
 pub use review::ReviewSummary;
 
@@ -50,39 +67,252 @@ pub fn begin(dirpath: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Generates a new empty revision file with the given name in the
-/// revisions directory specified by the provided config.
-pub fn plan(cfg: &Config, name: &str, contents: Option<&str>) -> Result<()> {
-    let timestamp = Utc::now().timestamp();
-    let next_id = RevisionFile::all(&cfg.revisions.directory)?
+/// Generates a new revision file with the given name in the revisions directory
+/// specified by the provided config, containing the given contents or else the
+/// named template (or default template if not given). If editing, the file is then
+/// opened in the user's editor and removed if left empty, in which case no path
+/// is returned.
+pub fn plan(
+    cfg: &Config,
+    name: &str,
+    contents: Option<&str>,
+    template: Option<&str>,
+    edit: bool,
+) -> Result<Option<PathBuf>> {
+    let last_id = RevisionFile::all(&cfg.revisions)?
         .iter()
         .reduce(|rf1, rf2| if rf1.id > rf2.id { rf1 } else { rf2 })
-        .map_or(0, |rf| rf.id)
-        + 1;
+        .map(|rf| rf.id);
 
-    let new_filename = format!("{:03}.{}.{}.sql", next_id, timestamp, name);
-    let new_path = cfg.revisions.directory.join(new_filename);
+    let name = &cfg.revisions.filenames.revision_name(name)?;
+    let created_at = Utc::now();
+    let next_id = cfg.revisions.ids.next_id(last_id, created_at);
+    let new_filename = cfg.revisions.filenames.filename(next_id, created_at, name);
+    let new_path = cfg.revisions.directory.join(&new_filename);
 
-    let contents = contents.map(|c| c.to_owned()).unwrap_or_else(|| {
-        format!(
-            "-- Revision: {name}
---
--- Add description here
+    let contents = match contents {
+        Some(contents) => contents.to_owned(),
+        None => template::render(
+            &template::load(&cfg.revisions, template)?,
+            &TemplateValues {
+                id: &cfg.revisions.filenames.id(next_id),
+                created_at,
+                name,
+                filename: &new_filename,
+            },
+        )?,
+    };
 
-begin;
+    fs::File::create(&new_path)?.write_all(contents.as_bytes())?;
 
--- Add SQL here
+    if edit {
+        if let Err(err) = open_editor(&new_path) {
+            // Nothing is lost by removing the revision if it was not changed
+            if fs::read_to_string(&new_path)? == contents {
+                fs::remove_file(&new_path)?;
+            }
+            return Err(err);
+        }
 
-commit;
-",
-            name = name
-        )
-    });
+        if fs::read_to_string(&new_path)?.trim().is_empty() {
+            fs::remove_file(&new_path)?;
+            info!("Removed {} since it was left empty", new_path.display());
 
-    fs::File::create(&new_path)?.write_all(contents.as_bytes())?;
+            return Ok(None);
+        }
+    }
+
+    // Only projects that opted into a lockfile with `jrny lock` keep one
+    if let Some(mut lockfile) = Lockfile::read(&cfg.lockfile)? {
+        lockfile.insert(&RevisionFile::load(&new_path, &cfg.revisions)?);
+        lockfile.write(&cfg.lockfile)?;
+    }
 
     info!("Created {}", new_path.display());
 
+    Ok(Some(new_path))
+}
+
+/// Writes all revisions and their down files to an archive at the path along with
+/// a manifest of their checksums, so that exactly these revisions can be applied
+/// later by reading them from the archive.
+pub fn package(cfg: &Config, path: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(Error::PathAlreadyExists(path.display().to_string()));
+    }
+
+    let revisions = RevisionFile::all(&cfg.revisions)?;
+
+    archive::write(path, &revisions)?;
+    info!(
+        "Packaged {} revision(s) into {}",
+        revisions.len(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Records the id, filename, and checksum of every revision in the lockfile
+/// next to the config file, replacing its previous contents.
+pub fn lock(cfg: &Config) -> Result<()> {
+    let files = RevisionFile::all(&cfg.revisions)?;
+
+    Lockfile::from_files(&files).write(&cfg.lockfile)?;
+    info!(
+        "Locked {} revision(s) in {}",
+        files.len(),
+        cfg.lockfile.display()
+    );
+
+    Ok(())
+}
+
+/// Signs every revision and its down file with the private key at the path, so that
+/// environments that trust the key can apply and revert them. The signed manifest is
+/// written next to the config file, replacing any previous one.
+pub fn sign(cfg: &Config, key_path: &Path) -> Result<()> {
+    let key = signature::read_signing_key(key_path)?;
+    let files = RevisionFile::all(&cfg.revisions)?;
+
+    SignedManifest::sign(&files, &key).write(&cfg.signed_manifest)?;
+    info!(
+        "Signed {} revision(s) in {}",
+        files.len(),
+        cfg.signed_manifest.display()
+    );
+    info!("");
+    info!(
+        "Public key: {}",
+        signature::encode(key.verifying_key().as_bytes())
+    );
+
+    Ok(())
+}
+
+/// Checks that the revisions and their down files are unchanged in the signed manifest,
+/// if the environment trusts any keys. Fails if the manifest is missing or not signed
+/// by a trusted key. Nothing needs to be fetched, so this works offline.
+fn check_signed(cfg: &Config, env: &Environment, revisions: &[&RevisionFile]) -> Result<()> {
+    if env.signing.trusted_keys.is_empty() {
+        return Ok(());
+    }
+
+    let trusted = env
+        .signing
+        .trusted_keys
+        .iter()
+        .map(|key| signature::parse_verifying_key(key))
+        .collect::<Result<Vec<_>>>()?;
+
+    let pathstr = cfg.signed_manifest.display().to_string();
+    let manifest = SignedManifest::read(&cfg.signed_manifest)?
+        .ok_or_else(|| Error::SignedManifestNotFound(pathstr.clone()))?;
+
+    if !manifest.verify(&trusted) {
+        return Err(Error::SignatureNotTrusted(pathstr));
+    }
+
+    let mut unsigned = Vec::new();
+
+    for revision in revisions {
+        if !manifest.covers(revision) {
+            unsigned.push(&revision.filename);
+        }
+    }
+
+    if unsigned.is_empty() {
+        return Ok(());
+    }
+
+    warn!("");
+    warn!("Not signed:");
+    for filename in &unsigned {
+        warn!("  {}", filename);
+    }
+
+    Err(Error::RevisionsNotSigned(unsigned.len(), pathstr))
+}
+
+/// Compares the revision files against the lockfile without connecting to a database,
+/// failing if any revision has changed or been removed since being locked, has a
+/// duplicate id, or has been inserted before revisions that are already locked.
+pub fn check(cfg: &Config) -> Result<()> {
+    let pathstr = cfg.lockfile.display().to_string();
+    let lockfile =
+        Lockfile::read(&cfg.lockfile)?.ok_or_else(|| Error::LockfileNotFound(pathstr.clone()))?;
+    let files = RevisionFile::all(&cfg.revisions)?;
+    let check = lockfile.check(&files)?;
+
+    info!("Checked {} revision(s) against {}", files.len(), pathstr);
+
+    if !check.unlocked.is_empty() {
+        info!("");
+        info!("Not yet locked:");
+        for filename in &check.unlocked {
+            info!("  {}", filename);
+        }
+    }
+
+    if check.problems.is_empty() {
+        return Ok(());
+    }
+
+    warn!("");
+    warn!("Errors:");
+    for (filename, problem) in &check.problems {
+        warn!("  {}: {}", filename, problem);
+    }
+
+    Err(Error::RevisionsFailedCheck(check.failed(), pathstr))
+}
+
+/// Compares the schemas of the databases at the given urls through the Postgres catalogs,
+/// returning the contents of a new revision with the name that changes the schema of the
+/// first into that of the second, if they differ. The tables tracking applied revisions
+/// are left out of the comparison.
+pub fn diff(cfg: &Config, name: &str, from_url: &str, to_url: &str) -> Result<Option<String>> {
+    let tables = tracking_tables(cfg);
+    let load = |url: &str| {
+        let mut client = Client::try_from(&Environment::from_database_url(url))?;
+
+        Catalog::load(&mut client, &cfg.table.schema, &tables, &[])
+    };
+
+    let statements = diff::statements(&load(from_url)?, &load(to_url)?);
+
+    if statements.is_empty() {
+        return Ok(None);
+    }
+
+    let name = cfg.revisions.filenames.revision_name(name)?;
+
+    Ok(Some(diff::diff_contents(&name, &statements)))
+}
+
+/// Opens the file in the editor given by `$VISUAL` or `$EDITOR` (defaulting to `vi`),
+/// waiting for it to exit.
+fn open_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // Editors are often configured with arguments, eg. `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::EditorFailed(editor.clone()))?;
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|_| Error::EditorFailed(editor.clone()))?;
+
+    if !status.success() {
+        return Err(Error::EditorFailed(editor));
+    }
+
     Ok(())
 }
 
@@ -90,7 +320,7 @@ commit;
 /// their status in the database.
 pub fn review(cfg: &Config, env: &Environment) -> Result<()> {
     let mut exec = Executor::new(cfg, env)?;
-    let review = Review::new(&mut exec, &cfg.revisions.directory)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
 
     if review.items().is_empty() {
         info!("No revisions found. Create your first revision with `jrny plan <some-name>`.");
@@ -108,11 +338,47 @@ pub fn review(cfg: &Config, env: &Environment) -> Result<()> {
             info!("    Applied on {}", format_local(*applied_on));
         }
 
-        if !item.problems().is_empty() {
+        if item.faked() {
+            info!("    Marked as applied without being run by jrny");
+        }
+
+        if let Some(filename) = item.renamed_from() {
+            info!("    Applied as {}", filename);
+        }
+
+        for repair in item.repairs() {
+            info!(
+                "    Repaired on {} by {}: {}",
+                format_local(repair.repaired_on),
+                repair.repaired_by,
+                repair.reason
+            );
+        }
+
+        if item.baselined() > 0 {
+            info!("    Applied as {} baselined revision(s)", item.baselined());
+        }
+
+        let (errors, warnings): (Vec<&_>, Vec<&_>) = item
+            .findings()
+            .iter()
+            .partition(|finding| finding.level == LintLevel::Error);
+
+        if !item.problems().is_empty() || !errors.is_empty() {
             warn!("    Errors:");
             for prob in item.problems() {
                 warn!("      - {}", prob);
             }
+            for finding in errors {
+                warn!("      - {}", finding);
+            }
+        }
+
+        if !warnings.is_empty() {
+            warn!("    Warnings:");
+            for finding in warnings {
+                warn!("      - {}", finding);
+            }
         }
     }
 
@@ -123,11 +389,127 @@ pub fn review(cfg: &Config, env: &Environment) -> Result<()> {
     Ok(())
 }
 
+/// Reviews the revisions against those as of a git commit, eg. `origin/main`, without
+/// connecting to a database, listing new revisions and failing if any revision of the
+/// commit has been changed, renamed, or removed, or if any new revision has an id that
+/// does not come after all of those of the commit.
+pub fn review_against(cfg: &Config, git_ref: &str) -> Result<()> {
+    let review = Review::against_git_ref(&cfg.revisions, git_ref)?;
+    let listed: Vec<_> = review
+        .items()
+        .iter()
+        .filter(|item| item.pending() || !item.problems().is_empty())
+        .collect();
+
+    if listed.is_empty() {
+        info!("No revisions differ from {}", git_ref);
+        return Ok(());
+    }
+
+    info!("Compared with {}:", git_ref);
+
+    for item in &listed {
+        info!("");
+        info!("  [{}] {}", item.id(), item.name());
+        info!("    Created on {}", format_local(*item.created_at()));
+
+        if item.pending() {
+            info!("    New since {}", git_ref);
+        }
+
+        if let Some(filename) = item.renamed_from() {
+            info!("    Named {} in {}", filename, git_ref);
+        }
+
+        if !item.problems().is_empty() {
+            warn!("    Errors:");
+            for prob in item.problems() {
+                warn!("      - {}", prob);
+            }
+        }
+    }
+
+    let failed = listed
+        .iter()
+        .filter(|item| !item.problems().is_empty())
+        .count();
+
+    if failed > 0 {
+        return Err(Error::RevisionsFailedGitReview(failed, git_ref.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Lints the revision files at the given paths for risky patterns, or the pending
+/// revisions of the database specified by the environment if no paths are given.
+/// Fails only if any findings are at the error level.
+pub fn lint(cfg: &Config, env: Option<&Environment>, paths: &[PathBuf]) -> Result<()> {
+    let linted = |file: &RevisionFile| {
+        (
+            file.filename.clone(),
+            crate::lint::lint(&file.contents, &cfg.revisions.lint),
+        )
+    };
+
+    let results: Vec<_> = match env {
+        Some(env) => {
+            let mut exec = Executor::new(cfg, env)?;
+            let review = Review::new(&mut exec, &cfg.revisions)?;
+
+            review.pending_revisions().into_iter().map(linted).collect()
+        }
+        None => paths
+            .iter()
+            .map(|path| RevisionFile::load(path, &cfg.revisions).map(|file| linted(&file)))
+            .collect::<Result<_>>()?,
+    };
+
+    if results.is_empty() {
+        info!("No revisions to lint");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+
+    for (filename, findings) in &results {
+        if findings.is_empty() {
+            info!("  {}: no findings", filename);
+            continue;
+        }
+
+        warn!("  {}:", filename);
+        for finding in findings {
+            warn!("    - {} {}", finding.level, finding);
+        }
+
+        if findings
+            .iter()
+            .any(|finding| finding.level == LintLevel::Error)
+        {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::RevisionsFailedLint(failed));
+    }
+
+    Ok(())
+}
+
 /// Applies all pending revisions specified by the given config to the
-/// database specified by the environment.
-pub fn embark(cfg: &Config, env: &Environment, through_id: Option<i32>) -> Result<()> {
+/// database specified by the environment. If faking, the revisions are
+/// only recorded as applied without being run. If the environment trusts
+/// any signing keys, nothing is applied unless all of the revisions are
+/// in a manifest signed by one of them.
+pub fn embark(cfg: &Config, env: &Environment, through_id: Option<i64>, fake: bool) -> Result<()> {
     let mut exec = Executor::new(cfg, env)?;
-    let review = Review::new(&mut exec, &cfg.revisions.directory)?;
+
+    // Widened before anything else, so that timestamp ids can be recorded
+    exec.upgrade_table()?;
+
+    let review = Review::new(&mut exec, &cfg.revisions)?;
 
     if review.failed() {
         return Err(Error::RevisionsFailedReview(review.summary().to_owned()));
@@ -137,9 +519,20 @@ pub fn embark(cfg: &Config, env: &Environment, through_id: Option<i32>) -> Resul
 
     if pending.is_empty() {
         info!("No revisions to apply");
-        return Ok(());
+        return write_configured_snapshot(&mut exec, cfg);
     }
 
+    // Checked up front so that nothing is applied if any revision is not signed
+    let to_check: Vec<&RevisionFile> = pending
+        .iter()
+        .filter(|rev| !matches!(through_id, Some(through_id) if rev.id > through_id))
+        .copied()
+        .collect();
+
+    check_signed(cfg, env, &to_check)?;
+
+    let action = if fake { "Faking" } else { "Applying" };
+
     let to_apply = match through_id {
         Some(through_id) => {
             let to_apply: Vec<&RevisionFile> = pending.iter().filter(|rev| rev.id <= through_id).copied().collect();
@@ -148,24 +541,37 @@ pub fn embark(cfg: &Config, env: &Environment, through_id: Option<i32>) -> Resul
             match (to_apply.as_slice(), to_skip.as_slice()) {
                 ([], []) => unreachable!("pending revisions should not be empty"),
                 (_, []) => {
-                    info!("Applying {} revision(s)", to_apply.len());
+                    info!("{} {} revision(s)", action, to_apply.len());
                 },
                 ([], _) => {
                     info!("No revisions to apply, skipping {} revision(s)", to_skip.len());
                 },
                 _ => {
-                    info!("Applying {} revision(s), skipping {}", to_apply.len(), to_skip.len());
+                    info!("{} {} revision(s), skipping {}", action, to_apply.len(), to_skip.len());
                 },
             }
 
             to_apply
         },
         None => {
-            info!("Applying {} revision(s)", pending.len());
+            info!("{} {} revision(s)", action, pending.len());
             pending
         },
     };
 
+    if fake {
+        fake_revisions(&mut exec, &to_apply)?;
+        return write_configured_snapshot(&mut exec, cfg);
+    }
+
+    // Substitute variables up front so that a missing value in a later revision
+    // does not leave the sequence partially applied.
+    let variables = cfg.variables_for(env);
+
+    for revision in &to_apply {
+        revision.render(&variables)?;
+    }
+
     if !to_apply.is_empty() {
         info!("");
         for revision in &to_apply {
@@ -174,6 +580,558 @@ pub fn embark(cfg: &Config, env: &Environment, through_id: Option<i32>) -> Resul
         }
     }
 
+    write_configured_snapshot(&mut exec, cfg)
+}
+
+/// Writes a snapshot of the schema if one is configured.
+fn write_configured_snapshot(exec: &mut Executor, cfg: &Config) -> Result<()> {
+    match &cfg.snapshot {
+        Some(settings) => write_snapshot(exec, settings),
+        None => Ok(()),
+    }
+}
+
+fn write_snapshot(exec: &mut Executor, settings: &SnapshotSettings) -> Result<()> {
+    let snapshot = snapshot::render(&exec.catalog(&settings.schemas)?);
+
+    fs::write(&settings.path, snapshot)?;
+    info!("");
+    info!("Wrote schema snapshot to {}", settings.path.display());
+
+    Ok(())
+}
+
+/// Writes a snapshot of the schema of the database specified by the environment to the
+/// configured path, as a sorted text dump built from the Postgres catalogs. All revisions
+/// must have been applied. If only checking, this instead fails if the existing snapshot
+/// does not match the schema, showing the differences.
+pub fn snapshot(cfg: &Config, env: &Environment, check: bool) -> Result<()> {
+    let settings = cfg.snapshot.as_ref().ok_or(Error::SnapshotNotConfigured)?;
+
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    if review.failed() {
+        return Err(Error::RevisionsFailedReview(review.summary().to_owned()));
+    }
+
+    // The snapshot would not show the schema that the revisions produce otherwise
+    let pending = review.pending_revisions();
+
+    if !pending.is_empty() {
+        return Err(Error::SnapshotRevisionsPending(pending.len()));
+    }
+
+    if !check {
+        return write_snapshot(&mut exec, settings);
+    }
+
+    let committed = snapshot::read(&settings.path)?;
+    let current = snapshot::render(&exec.catalog(&settings.schemas)?);
+
+    if committed != current {
+        info!("{}", snapshot::diff(&committed, &current));
+        return Err(Error::SnapshotOutdated(settings.path.display().to_string()));
+    }
+
+    info!("{} matches the schema", settings.path.display());
+
+    Ok(())
+}
+
+/// Applies every revision in order to a new database created on the server specified by
+/// the environment, reporting how long each took or which failed, eg. to find revisions
+/// that can no longer be applied to an empty database. The new database is dropped
+/// afterwards unless keeping it.
+pub fn verify(cfg: &Config, env: &Environment, keep: bool) -> Result<()> {
+    let mut revisions = RevisionFile::all(&cfg.revisions)?;
+
+    if revisions.is_empty() {
+        info!("No revisions to verify");
+        return Ok(());
+    }
+
+    revisions.sort_by_key(|revision| revision.id);
+
+    let dbname = format!(
+        "jrny_verify_{}_{}",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        process::id()
+    );
+    let mut server = Client::try_from(env)?;
+
+    server.batch_execute(&format!("CREATE DATABASE {}", dbname))?;
+    info!("Created database {}", dbname);
+
+    let result = apply_all(cfg, env, &dbname, &revisions);
+
+    info!("");
+    if keep {
+        info!("Kept database {}", dbname);
+    } else {
+        server.batch_execute(&format!("DROP DATABASE {}", dbname))?;
+        info!("Dropped database {}", dbname);
+    }
+
+    result
+}
+
+/// Applies the revisions in order to the named database, logging how long each took.
+fn apply_all(
+    cfg: &Config,
+    env: &Environment,
+    dbname: &str,
+    revisions: &[RevisionFile],
+) -> Result<()> {
+    let mut exec = Executor::for_database(cfg, env, dbname)?;
+    let started = Instant::now();
+
+    exec.ensure_table_exists()?;
+
+    info!("");
+    info!("Applying {} revision(s)", revisions.len());
+    info!("");
+
+    for revision in revisions {
+        let start = Instant::now();
+
+        if let Err(err) = exec.run_revision(revision) {
+            warn!("  {} failed", revision.filename);
+            return Err(Error::VerifyRevisionFailed(
+                revision.filename.clone(),
+                Box::new(err),
+            ));
+        }
+
+        info!(
+            "  {} ({} ms)",
+            revision.filename,
+            start.elapsed().as_millis()
+        );
+    }
+
+    info!("");
+    info!(
+        "Applied all revisions in {} ms",
+        started.elapsed().as_millis()
+    );
+
+    Ok(())
+}
+
+/// Records the pending revisions with the given ids as applied without running them,
+/// eg. when adopting an existing database or after applying a revision by hand.
+pub fn mark_applied(cfg: &Config, env: &Environment, ids: &[i64]) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    if review.failed() {
+        return Err(Error::RevisionsFailedReview(review.summary().to_owned()));
+    }
+
+    let pending = review.pending_revisions();
+
+    let not_pending = ids
+        .iter()
+        .find(|id| !pending.iter().any(|rev| rev.id == **id));
+
+    if let Some(id) = not_pending {
+        return Err(Error::RevisionNotPending(*id));
+    }
+
+    let to_mark: Vec<&RevisionFile> = pending
+        .into_iter()
+        .filter(|rev| ids.contains(&rev.id))
+        .collect();
+
+    info!("Marking {} revision(s) as applied", to_mark.len());
+
+    fake_revisions(&mut exec, &to_mark)
+}
+
+/// Records each of the revisions as applied without running them.
+fn fake_revisions(exec: &mut Executor, revisions: &[&RevisionFile]) -> Result<()> {
+    if !revisions.is_empty() {
+        info!("");
+        for revision in revisions {
+            info!("  {} (not run)", revision.filename);
+            exec.fake_revision(revision)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Which applied revisions to revert, counting back from the most recently applied.
+#[derive(Clone, Copy, Debug)]
+pub enum RevertTarget {
+    /// Reverts all applied revisions with an id greater than the given id
+    To(i64),
+    /// Reverts the given number of most recently applied revisions
+    Last(usize),
+}
+
+/// Reverts applied revisions in reverse order by running their down files,
+/// removing their records from the database specified by the environment in
+/// the same transaction.
+/// No revisions are reverted if any targeted revision is missing a down file,
+/// if the down file has changed since the revision was applied, or if the
+/// environment trusts signing keys and the down file is not signed.
+pub fn revert(cfg: &Config, env: &Environment, target: RevertTarget) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    if review.failed() {
+        return Err(Error::RevisionsFailedReview(review.summary().to_owned()));
+    }
+
+    let applied = review.applied_revisions();
+    let to_revert: Vec<_> = match target {
+        RevertTarget::To(to_id) => applied.iter().filter(|(file, _)| file.id > to_id).collect(),
+        RevertTarget::Last(count) => applied.iter().rev().take(count).rev().collect(),
+    };
+
+    if to_revert.is_empty() {
+        info!("No revisions to revert");
+        return Ok(());
+    }
+
+    // Check every down file before running any, so that a problem with an earlier
+    // revision does not leave the sequence partially reverted.
+    let mut downs = Vec::new();
+
+    for (file, record) in to_revert.iter().rev() {
+        let down = file.down_for(record)?;
+        let down_sql = down.render(&cfg.variables_for(env))?;
+
+        // The down file runs within a transaction, which these would end early
+        if let Some(cmd) = sql::transaction_command(&down_sql) {
+            return Err(Error::TransactionCommandFound(cmd.to_string()));
+        }
+
+        downs.push((*record, down));
+    }
+
+    let files: Vec<&RevisionFile> = to_revert.iter().map(|(file, _)| *file).collect();
+
+    check_signed(cfg, env, &files)?;
+
+    info!("Reverting {} revision(s)", downs.len());
+    info!("");
+
+    for (record, down) in downs {
+        info!("  {}", down.filename);
+        exec.revert_revision(record, down)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a single baseline revision with the schema as of the given revision id,
+/// dumped from the database specified by the environment using `pg_dump`, and moves
+/// the revisions it stands in for into an `archive` subdirectory of the revisions
+/// directory. The database must have all revisions through the given id applied
+/// and none after it.
+///
+/// New databases will apply the baseline in place of the archived revisions, while
+/// databases that already applied those revisions will treat the baseline as applied.
+pub fn baseline(cfg: &Config, env: &Environment, through_id: i64) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    if review.failed() {
+        return Err(Error::RevisionsFailedReview(review.summary().to_owned()));
+    }
+
+    // The dumped schema would not represent the revision otherwise
+    let at_revision = review.items().iter().all(|item| {
+        (item.id() <= through_id && item.applied() && item.baselined() == 0)
+            || (item.id() > through_id && item.pending())
+    });
+
+    let to_archive: Vec<_> = review
+        .applied_revisions()
+        .into_iter()
+        .filter(|(file, _)| file.id <= through_id)
+        .collect();
+
+    if !at_revision || to_archive.is_empty() {
+        return Err(Error::BaselineStateMismatch(through_id));
+    }
+
+    let baselined: Vec<BaselinedRevision> = to_archive
+        .iter()
+        .map(|(_, record)| BaselinedRevision {
+            filename: record.filename.clone(),
+            checksum_algorithm: record.checksum_algorithm,
+            checksum: record.checksum.clone(),
+        })
+        .collect();
+
+    let schema = baseline::dump_schema(cfg, env)?;
+    let contents = baseline::baseline_contents(through_id, &baselined, &schema);
+
+    let paths: Vec<_> = to_archive
+        .iter()
+        .flat_map(|(file, _)| [Some(&file.path), file.down.as_ref().map(|down| &down.path)])
+        .flatten()
+        .collect();
+
+    let new_filename = cfg
+        .revisions
+        .filenames
+        .filename(through_id, Utc::now(), "baseline");
+    let new_path = cfg.revisions.directory.join(new_filename);
+
+    if new_path.exists() {
+        return Err(Error::PathAlreadyExists(new_path.display().to_string()));
+    }
+
+    let archive_dir = baseline::archive(&cfg.revisions.directory, &paths)?;
+
+    fs::File::create(&new_path)?.write_all(contents.as_bytes())?;
+
+    info!(
+        "Archived {} revision(s) to {}",
+        to_archive.len(),
+        archive_dir.display()
+    );
+    info!("Created {}", new_path.display());
+
+    Ok(())
+}
+
+/// Accepts the renaming of applied revision files by updating the filenames (and
+/// the names and timestamps within them) recorded for those revisions.
+pub fn accept_renames(cfg: &Config, env: &Environment) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+    let renamed = review.renamed_revisions();
+
+    if renamed.is_empty() {
+        info!("No renamed revisions to accept");
+        return Ok(());
+    }
+
+    info!("Accepting {} renamed revision(s)", renamed.len());
+    info!("");
+
+    for (file, record) in renamed {
+        exec.rename_revision(record, file)?;
+        info!("  {} -> {}", record.filename, file.filename);
+    }
+
+    Ok(())
+}
+
+/// Accepts an intentional change to an applied revision, eg. to fix a comment, by
+/// updating its recorded checksum after showing the change (if the applied contents
+/// can be found in the git history) and asking for a reason if not given.
+/// The previous checksum is recorded along with the reason and who made the change.
+/// A record that no longer follows from the one before it is accepted the same way.
+pub fn repair(cfg: &Config, env: &Environment, id: i64, reason: Option<&str>) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    let (file, record) = review
+        .changed_revision(id)
+        .or_else(|| review.unlinked_revision(id))
+        .ok_or(Error::RevisionNotChanged(id))?;
+    let checksum = file.checksum_with(record.checksum_algorithm);
+
+    info!("Repairing {}", file.filename);
+    info!("");
+
+    if checksum == record.checksum {
+        warn!("The record was changed outside of jrny, or records next to it were removed");
+    } else {
+        match repair::applied_contents(file, record) {
+            Some(applied) => info!("{}", repair::diff(&applied, &file.contents)),
+            None => warn!("The applied contents could not be found in the git history to compare"),
+        }
+    }
+
+    info!("  Previous checksum: {}", record.checksum);
+    info!("  New checksum:      {}", checksum);
+    info!("");
+
+    let reason = match reason {
+        Some(reason) => reason.to_string(),
+        None => repair::prompt_reason()?,
+    };
+
+    if reason.trim().is_empty() {
+        return Err(Error::RepairReasonMissing);
+    }
+
+    exec.repair_revision(
+        record,
+        &checksum,
+        reason.trim(),
+        repair::current_user().as_deref(),
+    )?;
+
+    info!("Repaired {}", file.filename);
+
+    Ok(())
+}
+
+/// Removes the record of an applied revision whose file no longer exists, eg. one
+/// applied from an abandoned branch, given either its id or filename. If given,
+/// the SQL file is run within the same transaction to clean up after the revision,
+/// with variables substituted as in revisions.
+/// Protected environments require that this is confirmed.
+pub fn forget(
+    cfg: &Config,
+    env: &Environment,
+    target: &str,
+    cleanup_path: Option<&Path>,
+    confirmed: bool,
+) -> Result<()> {
+    if env.database.protected && !confirmed {
+        return Err(Error::EnvProtected);
+    }
+
+    let cleanup = cleanup_path
+        .map(|path| {
+            let contents = fs::read_to_string(path)?;
+
+            revisions::render(
+                &contents,
+                &path.display().to_string(),
+                &cfg.variables_for(env),
+            )
+        })
+        .transpose()?;
+
+    // The cleanup runs within a transaction, which these would end early
+    if let Some(cmd) = cleanup.as_deref().and_then(sql::transaction_command) {
+        return Err(Error::TransactionCommandFound(cmd.to_string()));
+    }
+
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    let matches = |id: i64, filename: &str| {
+        target.parse() == Ok(id) || filename == target || filename_stem(filename) == target
+    };
+
+    let missing = review
+        .missing_revisions()
+        .into_iter()
+        .find(|record| matches(record.id, &record.filename));
+
+    let record = match missing {
+        Some(record) => record,
+        None => {
+            let applied = review
+                .applied_revisions()
+                .into_iter()
+                .find(|(_, record)| matches(record.id, &record.filename));
+
+            return Err(match applied {
+                Some((file, _)) => Error::RevisionFileExists(file.filename.clone()),
+                None => Error::RevisionRecordNotFound(target.to_string()),
+            });
+        }
+    };
+
+    info!("Forgetting {}", record.filename);
+
+    if let Some(path) = cleanup_path {
+        info!("");
+        info!("  Running {}", path.display());
+    }
+
+    exec.forget_revision(record, cleanup.as_deref())?;
+
+    Ok(())
+}
+
+/// Upgrades the tables tracking applied revisions in the database specified by the
+/// environment, which rewrites tables created before revision ids were BIGINTs.
+/// This is otherwise only done by `embark`, since rewriting locks the tables.
+pub fn upgrade(cfg: &Config, env: &Environment) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+
+    let upgraded = exec.upgrade_table()?;
+    exec.ensure_table_exists()?;
+
+    if upgraded {
+        info!("Upgraded table {}.{}", cfg.table.schema, cfg.table.name);
+    } else {
+        info!(
+            "Table {}.{} is up to date",
+            cfg.table.schema, cfg.table.name
+        );
+    }
+
+    Ok(())
+}
+
+/// Renumbers pending revisions so that they follow the last applied revision and each
+/// other while keeping their relative order, eg. to resolve duplicate ids after merging
+/// branches that each added revisions. Revisions recorded as applied are never renamed.
+/// If only checking, this reports the revisions that would be renumbered and fails if
+/// there are any.
+pub fn renumber(cfg: &Config, env: &Environment, check: bool) -> Result<()> {
+    let mut exec = Executor::new(cfg, env)?;
+    let review = Review::new(&mut exec, &cfg.revisions)?;
+
+    let last_applied_id = review
+        .items()
+        .iter()
+        .filter(|item| item.applied())
+        .map(|item| item.id())
+        .max();
+
+    let renumberings = renumber::renumberings(
+        review.pending_revisions(),
+        last_applied_id,
+        &cfg.revisions.filenames,
+    );
+
+    if renumberings.is_empty() {
+        info!("No revisions to renumber");
+        return Ok(());
+    }
+
+    renumber::check_conflicts(&renumberings, &review.recorded_filenames())?;
+
+    if check {
+        info!("{} revision(s) would be renumbered", renumberings.len());
+    } else {
+        info!("Renumbering {} revision(s)", renumberings.len());
+    }
+    info!("");
+
+    for renumbering in &renumberings {
+        info!(
+            "  {} -> {}",
+            renumbering.file.filename,
+            renumbering.new_filename()
+        );
+    }
+
+    if check {
+        return Err(Error::RevisionsNeedRenumbering(renumberings.len()));
+    }
+
+    // Later revisions are renamed first, moving them out of the way of any
+    // earlier revisions that are taking their place
+    for renumbering in renumberings.iter().rev() {
+        renumbering.apply()?;
+    }
+
+    if let Some(mut lockfile) = Lockfile::read(&cfg.lockfile)? {
+        renumber::relock(
+            &renumberings,
+            &mut lockfile,
+            &RevisionFile::all(&cfg.revisions)?,
+        );
+        lockfile.write(&cfg.lockfile)?;
+    }
+
     Ok(())
 }
 
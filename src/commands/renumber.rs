@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::context::FilenameScheme;
+use crate::lockfile::Lockfile;
+use crate::revisions::{filename_stem, RevisionFile};
+use crate::{Error, Result};
+
+/// A pending revision to be given a new id, along with its down file if present.
+#[derive(Debug)]
+pub(super) struct Renumbering<'a> {
+    pub file: &'a RevisionFile,
+    pub new_id: i64,
+    scheme: &'a FilenameScheme,
+}
+
+impl Renumbering<'_> {
+    /// The new filename for the revision.
+    pub fn new_filename(&self) -> String {
+        renumbered(&self.file.filename, &self.scheme.id(self.new_id))
+    }
+
+    /// The new path of the revision.
+    fn new_path(&self) -> PathBuf {
+        self.file.path.with_file_name(self.new_filename())
+    }
+
+    /// Each current path of the revision and its down file, paired with its new path.
+    fn moves(&self) -> Vec<(&Path, PathBuf)> {
+        let mut moves = vec![(self.file.path.as_path(), self.new_path())];
+
+        if let Some(down) = &self.file.down {
+            moves.push((
+                down.path.as_path(),
+                down.path
+                    .with_file_name(renumbered(&down.filename, &self.scheme.id(self.new_id))),
+            ));
+        }
+
+        moves
+    }
+
+    /// Renames the revision (and its down file), replacing mentions of its old name
+    /// within the comments at the start of the file, eg. a template header.
+    pub fn apply(&self) -> Result<()> {
+        let old_stem = filename_stem(&self.file.filename);
+        let new_filename = self.new_filename();
+        let new_stem = filename_stem(&new_filename);
+
+        for (path, new_path) in self.moves() {
+            fs::rename(path, &new_path)?;
+
+            // Compressed files are left as-is rather than recompressed
+            if new_path.extension().and_then(|ext| ext.to_str()) == Some("sql") {
+                let contents = fs::read_to_string(&new_path)?;
+                let updated = replace_in_header(&contents, old_stem, new_stem);
+
+                if updated != contents {
+                    fs::write(&new_path, updated)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Assigns new ids to the given pending revisions, which must be ordered as they are
+/// reviewed, so that they follow the given id (if any) and each other while keeping
+/// their relative order. Revisions that already meet that are not renumbered.
+pub(super) fn renumberings<'a>(
+    pending: Vec<&'a RevisionFile>,
+    last_applied_id: Option<i64>,
+    scheme: &'a FilenameScheme,
+) -> Vec<Renumbering<'a>> {
+    let mut next_id = last_applied_id.map_or(1, |id| id + 1);
+    let mut renumberings = Vec::new();
+
+    for file in pending {
+        let new_id = file.id.max(next_id);
+
+        if new_id != file.id {
+            renumberings.push(Renumbering {
+                file,
+                new_id,
+                scheme,
+            });
+        }
+        next_id = new_id + 1;
+    }
+
+    renumberings
+}
+
+/// Moves the lockfile's records of the renumbered revisions (if recorded) to their new
+/// filenames, given the revision files after renaming, whose checksums also change if
+/// their headers mentioned the old names.
+pub(super) fn relock(
+    renumberings: &[Renumbering],
+    lockfile: &mut Lockfile,
+    files: &[RevisionFile],
+) {
+    let relocked: Vec<String> = renumberings
+        .iter()
+        .filter(|renumbering| lockfile.remove(&renumbering.file.filename))
+        .map(|renumbering| renumbering.new_filename())
+        .collect();
+
+    for file in files
+        .iter()
+        .filter(|file| relocked.contains(&file.filename))
+    {
+        lockfile.insert(file);
+    }
+}
+
+/// Fails if any of the renamed files would replace an existing file or take the
+/// name of a revision already recorded as applied.
+pub(super) fn check_conflicts(renumberings: &[Renumbering], recorded: &[&str]) -> Result<()> {
+    let renamed: Vec<&Path> = renumberings
+        .iter()
+        .flat_map(|r| r.moves())
+        .map(|(path, _)| path)
+        .collect();
+
+    for renumbering in renumberings {
+        let new_stem = filename_stem(&renumbering.new_filename()).to_string();
+
+        if recorded
+            .iter()
+            .any(|filename| filename_stem(filename) == new_stem)
+        {
+            return Err(Error::RevisionAlreadyApplied(new_stem));
+        }
+
+        for (_, new_path) in renumbering.moves() {
+            // Another renamed file will have moved out of the way first if it
+            // is later in the sequence, but not if it is earlier.
+            if new_path.exists() && !renamed.contains(&new_path.as_path()) {
+                return Err(Error::PathAlreadyExists(new_path.display().to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The filename with its leading id replaced by the new id.
+fn renumbered(filename: &str, new_id: &str) -> String {
+    let rest = filename.trim_start_matches(|c: char| c.is_ascii_digit());
+
+    format!("{}{}", new_id, rest)
+}
+
+fn replace_in_header(contents: &str, old: &str, new: &str) -> String {
+    let mut replaced = String::with_capacity(contents.len());
+    let mut in_header = true;
+
+    for line in contents.split_inclusive('\n') {
+        in_header = in_header && line.trim_start().starts_with("--");
+
+        if in_header {
+            replaced.push_str(&line.replace(old, new));
+        } else {
+            replaced.push_str(line);
+        }
+    }
+
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revisions::test_settings;
+
+    #[test]
+    fn renumbered_replaces_leading_id() {
+        assert_eq!(
+            renumbered("042.1618370298.add-users.sql.gz", "043"),
+            "043.1618370298.add-users.sql.gz"
+        );
+        assert_eq!(
+            renumbered("999_20210414031818_x.down.sql", "01000"),
+            "01000_20210414031818_x.down.sql"
+        );
+    }
+
+    #[test]
+    fn replace_in_header_only_replaces_leading_comments() {
+        assert_eq!(
+            replace_in_header(
+                "-- Revision: 042.1.add\n--\nselect '042.1.add';\n",
+                "042.1.add",
+                "043.1.add"
+            ),
+            "-- Revision: 043.1.add\n--\nselect '042.1.add';\n"
+        );
+    }
+
+    #[test]
+    fn relock_moves_records_to_the_new_filenames() {
+        let dir = std::env::temp_dir().join(format!("jrny-relock-{}", std::process::id()));
+        let settings = test_settings(&dir);
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("001.1577836800.a.sql"), "select 1;").unwrap();
+        fs::write(
+            dir.join("002.1577836800.b.sql"),
+            "-- Revision: 002.1577836800.b\nselect 2;",
+        )
+        .unwrap();
+        fs::write(dir.join("002.1577836800.b.down.sql"), "select -2;").unwrap();
+
+        let result = (|| {
+            let files = RevisionFile::all(&settings)?;
+            let mut lockfile = Lockfile::from_files(&files);
+            let renumberings = renumberings(vec![&files[1]], Some(2), &settings.filenames);
+
+            for renumbering in renumberings.iter().rev() {
+                renumbering.apply()?;
+            }
+
+            let renamed = RevisionFile::all(&settings)?;
+            relock(&renumberings, &mut lockfile, &renamed);
+
+            Ok::<_, Error>((lockfile, renamed))
+        })();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (lockfile, renamed) = result.unwrap();
+
+        assert_eq!(lockfile, Lockfile::from_files(&renamed));
+        assert_eq!(lockfile.revisions[1].filename, "003.1577836800.b.sql");
+        assert!(lockfile.revisions[1].down_checksum.is_some());
+        assert!(lockfile.check(&renamed).unwrap().problems.is_empty());
+    }
+}
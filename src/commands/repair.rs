@@ -0,0 +1,91 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process::Command;
+
+use log::info;
+use similar::TextDiff;
+
+use crate::revisions::{decode_contents, RevisionFile, RevisionRecord};
+use crate::{Error, Result};
+
+/// Searches the git history of the revision file for the contents it had when it was
+/// applied, ie. the most recent version matching the recorded checksum, if any.
+///
+/// Since only the file itself is checked, this will not find the contents of
+/// revisions that include other files.
+pub(super) fn applied_contents(file: &RevisionFile, record: &RevisionRecord) -> Option<String> {
+    let dir = file.path.parent()?;
+    let filename = file.path.file_name()?.to_str()?;
+
+    let log = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "--format=%H", "--follow", "--", filename])
+        .output()
+        .ok()?;
+
+    if !log.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&log.stdout)
+        .lines()
+        .find_map(|commit| {
+            let show = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .arg("show")
+                .arg(format!("{}:./{}", commit, filename))
+                .output()
+                .ok()?;
+
+            if !show.status.success() {
+                return None;
+            }
+
+            let contents = decode_contents(&file.path, show.stdout.as_slice()).ok()?;
+
+            (record.checksum_algorithm.checksum(&contents) == record.checksum).then_some(contents)
+        })
+}
+
+/// A unified diff of the applied contents against the current contents.
+pub(super) fn diff(applied: &str, current: &str) -> String {
+    TextDiff::from_lines(applied, current)
+        .unified_diff()
+        .header("applied", "current")
+        .to_string()
+}
+
+/// Asks for the reason that the revision was changed, which must instead be given
+/// up front when there is no one at a terminal to ask.
+pub(super) fn prompt_reason() -> Result<String> {
+    let mut reason = String::new();
+
+    if !io::stdin().is_terminal() {
+        return Err(Error::RepairReasonMissing);
+    }
+
+    info!("Reason for the change:");
+    io::stdin().read_line(&mut reason)?;
+
+    Ok(reason)
+}
+
+/// The name of the user running the command, if known.
+pub(super) fn current_user() -> Option<String> {
+    env::var("USER").or_else(|_| env::var("USERNAME")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_shows_changed_lines() {
+        assert_eq!(
+            diff("-- Revison: a\nselect 1;\n", "-- Revision: a\nselect 1;\n"),
+            "--- applied\n+++ current\n@@ -1,2 +1,2 @@\n--- Revison: a\n+-- Revision: a\n select 1;\n"
+        );
+    }
+}
@@ -1,17 +1,22 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::path::Path;
 
 use chrono::{DateTime, Utc};
 
-use crate::revisions::{RevisionFile, RevisionRecord};
+use crate::context::{LintLevel, RevisionSource, RevisionsSettings};
+use crate::lint::{self, LintFinding};
+use crate::revisions::{broken_links, filename_stem, RevisionFile, RevisionRecord, RevisionRepair};
 use crate::{Executor, Result};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum RevisionProblem {
+    BaselineIncomplete,
+    ChainBroken,
+    ChainHeadMoved,
     DuplicateId,
     FileChanged,
     FileNotFound,
+    FileRenamed,
     PrecedesApplied,
 }
 
@@ -23,9 +28,15 @@ impl fmt::Display for RevisionProblem {
             f,
             "{}",
             match self {
+                BaselineIncomplete => "Only some of the baselined revisions have been applied",
+                ChainBroken =>
+                    "Record was changed outside of jrny, or follows a record that was removed",
+                ChainHeadMoved =>
+                    "Record was changed outside of jrny, or later records were removed",
                 DuplicateId => "Revision has a duplicate id",
                 FileChanged => "File has changed after being applied",
                 FileNotFound => "File could not be found",
+                FileRenamed => "File has been renamed after being applied",
                 PrecedesApplied => "Later revisions have already been applied",
             }
         )
@@ -42,6 +53,11 @@ enum ReviewItemSource {
     },
     FileOnly(RevisionFile),
     RecordOnly(RevisionRecord),
+    /// A baseline revision in a database that applied the revisions it stands in for
+    Baselined {
+        file: RevisionFile,
+        records: Vec<RevisionRecord>,
+    },
 }
 
 use ReviewItemSource::*;
@@ -53,26 +69,34 @@ pub struct ReviewItem {
 
     /// Problems identified with the revision
     problems: HashSet<RevisionProblem>,
+
+    /// Intentional changes accepted for the revision after it was applied
+    repairs: Vec<RevisionRepair>,
+
+    /// Risky patterns found by linting the revision, if it is pending
+    findings: Vec<LintFinding>,
 }
 
 impl ReviewItem {
-    pub fn id(&self) -> i32 {
+    pub fn id(&self) -> i64 {
         match &self.source {
-            FileAndRecord { file, .. } | FileOnly(file) => file.id,
+            FileAndRecord { file, .. } | FileOnly(file) | Baselined { file, .. } => file.id,
             RecordOnly(record) => record.id,
         }
     }
 
     pub fn name(&self) -> &str {
         match &self.source {
-            FileAndRecord { file, .. } | FileOnly(file) => &file.name,
+            FileAndRecord { file, .. } | FileOnly(file) | Baselined { file, .. } => &file.name,
             RecordOnly(record) => &record.name,
         }
     }
 
     pub fn created_at(&self) -> &DateTime<Utc> {
         match &self.source {
-            FileAndRecord { file, .. } | FileOnly(file) => &file.created_at,
+            FileAndRecord { file, .. } | FileOnly(file) | Baselined { file, .. } => {
+                &file.created_at
+            }
             RecordOnly(record) => &record.created_at,
         }
     }
@@ -81,13 +105,68 @@ impl ReviewItem {
         &self.problems
     }
 
+    pub fn repairs(&self) -> &[RevisionRepair] {
+        &self.repairs
+    }
+
+    pub fn findings(&self) -> &[LintFinding] {
+        &self.findings
+    }
+
+    /// Whether any lint finding is at the error level, failing the review.
+    pub fn lint_failed(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.level == LintLevel::Error)
+    }
+
     pub fn applied_on(&self) -> Option<&DateTime<Utc>> {
         match &self.source {
             FileAndRecord { record, .. } | RecordOnly(record) => Some(&record.applied_on),
+            Baselined { records, .. } => records.iter().map(|record| &record.applied_on).max(),
+            FileOnly(_) => None,
+        }
+    }
+
+    /// The number of applied revisions the revision stands in for, if it is a baseline.
+    pub fn baselined(&self) -> usize {
+        match &self.source {
+            Baselined { records, .. } => records.len(),
+            _ => 0,
+        }
+    }
+
+    /// The filename recorded when the revision was applied, if it has since been renamed.
+    pub fn renamed_from(&self) -> Option<&str> {
+        match &self.source {
+            // Compressing the file only changes its extension, which is not a rename
+            FileAndRecord { file, record }
+                if filename_stem(&file.filename) != filename_stem(&record.filename) =>
+            {
+                Some(&record.filename)
+            }
             _ => None,
         }
     }
 
+    /// The records of the revision, if it has been applied.
+    fn records(&self) -> Vec<&RevisionRecord> {
+        match &self.source {
+            FileAndRecord { record, .. } | RecordOnly(record) => vec![record],
+            Baselined { records, .. } => records.iter().collect(),
+            FileOnly(_) => vec![],
+        }
+    }
+
+    /// Whether the revision was recorded as applied without being run.
+    pub fn faked(&self) -> bool {
+        match &self.source {
+            FileAndRecord { record, .. } | RecordOnly(record) => record.faked,
+            Baselined { records, .. } => records.iter().any(|record| record.faked),
+            FileOnly(_) => false,
+        }
+    }
+
     pub fn applied(&self) -> bool {
         !self.pending()
     }
@@ -105,6 +184,8 @@ impl ReviewItem {
         Self {
             source: ReviewItemSource::FileAndRecord { file, record },
             problems,
+            repairs: Vec::new(),
+            findings: Vec::new(),
         }
     }
 
@@ -112,6 +193,21 @@ impl ReviewItem {
         Self {
             source: ReviewItemSource::FileOnly(file),
             problems,
+            repairs: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    fn baselined_records(
+        file: RevisionFile,
+        records: Vec<RevisionRecord>,
+        problems: RevisionProblems,
+    ) -> Self {
+        Self {
+            source: ReviewItemSource::Baselined { file, records },
+            problems,
+            repairs: Vec::new(),
+            findings: Vec::new(),
         }
     }
 
@@ -119,25 +215,84 @@ impl ReviewItem {
         Self {
             source: ReviewItemSource::RecordOnly(record),
             problems,
+            repairs: Vec::new(),
+            findings: Vec::new(),
         }
     }
 
     fn from_sources(files: Vec<RevisionFile>, records: Vec<RevisionRecord>) -> Vec<Self> {
         let mut items = Vec::new();
 
-        // For extracting the equivalent record when iterating through files
+        // For extracting the equivalent record when iterating through files,
+        // ignoring the extension so that compressing or decompressing an
+        // applied revision is not seen as removing it
         let mut records: HashMap<String, RevisionRecord> = records
             .into_iter()
-            .map(|record| (record.filename.clone(), record))
+            .map(|record| (filename_stem(&record.filename).to_string(), record))
             .collect();
 
-        for file in files {
+        // Baselines are matched first, since they stand in for the records of the
+        // revisions they collapsed in databases that applied those revisions instead
+        let (baselines, files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .partition(|file| !file.baselined.is_empty());
+
+        // Files without a record of the same name, which are matched after all
+        // files have been so that a renamed file cannot take another's record
+        let mut unmatched = Vec::new();
+
+        for file in baselines.into_iter().chain(files) {
             let mut problems = HashSet::new();
-            let item = match records.remove(&file.filename) {
+            let mut baselined = Vec::new();
+
+            for original in &file.baselined {
+                if let Some(record) = records.remove(filename_stem(&original.filename)) {
+                    if record.checksum_algorithm != original.checksum_algorithm
+                        || record.checksum != original.checksum
+                    {
+                        problems.insert(RevisionProblem::FileChanged);
+                    }
+                    baselined.push(record);
+                }
+            }
+
+            if !baselined.is_empty() {
+                if baselined.len() < file.baselined.len() {
+                    problems.insert(RevisionProblem::BaselineIncomplete);
+                }
+                items.push(Self::baselined_records(file, baselined, problems));
+                continue;
+            }
+
+            match records.remove(filename_stem(&file.filename)) {
                 Some(record) => {
-                    if file.checksum != record.checksum {
+                    // Applied revisions are always compared using the algorithm
+                    // recorded with them, regardless of the configured algorithm
+                    if file.checksum_with(record.checksum_algorithm) != record.checksum {
                         problems.insert(RevisionProblem::FileChanged);
                     }
+                    items.push(Self::file_and_record(file, record, problems));
+                }
+                None => unmatched.push(file),
+            }
+        }
+
+        for file in unmatched {
+            let mut problems = HashSet::new();
+
+            // A file with the id and contents of an unmatched record is that
+            // revision renamed after being applied, rather than a new revision
+            let renamed = records
+                .iter()
+                .find(|(_, record)| {
+                    record.id == file.id
+                        && file.checksum_with(record.checksum_algorithm) == record.checksum
+                })
+                .map(|(stem, _)| stem.clone());
+
+            let item = match renamed.and_then(|stem| records.remove(&stem)) {
+                Some(record) => {
+                    problems.insert(RevisionProblem::FileRenamed);
                     Self::file_and_record(file, record, problems)
                 }
                 None => Self::file_only(file, problems),
@@ -188,13 +343,25 @@ impl ReviewItem {
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct ReviewSummary {
+    baselines_incomplete: usize,
+    chains_broken: usize,
     duplicate_ids: usize,
     files_changed: usize,
     files_not_found: usize,
+    files_renamed: usize,
+    lint_failed: usize,
     preceding_applied: usize,
 }
 
 impl ReviewSummary {
+    pub fn baselines_incomplete(&self) -> usize {
+        self.baselines_incomplete
+    }
+
+    pub fn chains_broken(&self) -> usize {
+        self.chains_broken
+    }
+
     pub fn duplicate_ids(&self) -> usize {
         self.duplicate_ids
     }
@@ -207,6 +374,14 @@ impl ReviewSummary {
         self.files_not_found
     }
 
+    pub fn files_renamed(&self) -> usize {
+        self.files_renamed
+    }
+
+    pub fn lint_failed(&self) -> usize {
+        self.lint_failed
+    }
+
     pub fn preceding_applied(&self) -> usize {
         self.preceding_applied
     }
@@ -220,9 +395,13 @@ pub struct Review {
 
 impl Review {
     pub fn failed(&self) -> bool {
-        self.summary.duplicate_ids > 0
+        self.summary.baselines_incomplete > 0
+            || self.summary.chains_broken > 0
+            || self.summary.duplicate_ids > 0
             || self.summary.files_changed > 0
             || self.summary.files_not_found > 0
+            || self.summary.files_renamed > 0
+            || self.summary.lint_failed > 0
             || self.summary.preceding_applied > 0
     }
 
@@ -244,18 +423,184 @@ impl Review {
             .collect()
     }
 
-    pub fn new(exec: &mut Executor, revision_dir: &Path) -> Result<Self> {
-        use RevisionProblem::*;
+    /// Applied revisions that still have a corresponding file, ordered by id.
+    pub fn applied_revisions(&self) -> Vec<(&RevisionFile, &RevisionRecord)> {
+        self.items
+            .iter()
+            .filter_map(|item| match &item.source {
+                FileAndRecord { file, record } => Some((file, record)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The applied revision with the given id, if its file has changed since being applied.
+    pub fn changed_revision(&self, id: i64) -> Option<(&RevisionFile, &RevisionRecord)> {
+        self.items
+            .iter()
+            .filter(|item| item.problems.contains(&RevisionProblem::FileChanged))
+            .find_map(|item| match &item.source {
+                FileAndRecord { file, record } if record.id == id => Some((file, record)),
+                _ => None,
+            })
+    }
 
+    /// The applied revision with the given id, if its record does not follow from the
+    /// record before it, or it is the latest record and later records were removed.
+    pub fn unlinked_revision(&self, id: i64) -> Option<(&RevisionFile, &RevisionRecord)> {
+        self.items
+            .iter()
+            .filter(|item| {
+                item.problems.contains(&RevisionProblem::ChainBroken)
+                    || item.problems.contains(&RevisionProblem::ChainHeadMoved)
+            })
+            .find_map(|item| match &item.source {
+                FileAndRecord { file, record } if record.id == id => Some((file, record)),
+                _ => None,
+            })
+    }
+
+    /// Applied revisions whose files could not be found.
+    pub fn missing_revisions(&self) -> Vec<&RevisionRecord> {
+        self.items
+            .iter()
+            .filter_map(|item| match &item.source {
+                RecordOnly(record) => Some(record),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Applied revisions whose files have been renamed since being applied.
+    pub fn renamed_revisions(&self) -> Vec<(&RevisionFile, &RevisionRecord)> {
+        self.items
+            .iter()
+            .filter(|item| item.problems.contains(&RevisionProblem::FileRenamed))
+            .filter_map(|item| match &item.source {
+                FileAndRecord { file, record } => Some((file, record)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The filenames of all revisions recorded as applied, whether or not they
+    /// still have a corresponding file.
+    pub fn recorded_filenames(&self) -> Vec<&str> {
+        self.items
+            .iter()
+            .flat_map(|item| item.records())
+            .map(|record| record.filename.as_str())
+            .collect()
+    }
+
+    pub fn new(exec: &mut Executor, settings: &RevisionsSettings) -> Result<Self> {
         exec.ensure_table_exists()?;
 
-        let files = RevisionFile::all(revision_dir)?;
+        let files = RevisionFile::all(settings)?;
         let records = exec.load_revisions()?;
+        let broken = broken_links(&records);
+        let head_intact = exec.head_intact()?;
+
+        let mut items = ReviewItem::from_sources(files, records);
+
+        for item in &mut items {
+            if item
+                .records()
+                .iter()
+                .any(|record| broken.contains(&record.id))
+            {
+                item.problems.insert(RevisionProblem::ChainBroken);
+            }
+        }
+
+        // The latest record that remains shows that later records were removed,
+        // unless every record was removed
+        let mut all_removed = false;
+
+        if !head_intact {
+            let latest = items
+                .iter_mut()
+                .filter(|item| !item.records().is_empty())
+                .max_by_key(|item| item.records().iter().map(|record| record.id).max());
+
+            match latest {
+                Some(item) => {
+                    item.problems.insert(RevisionProblem::ChainHeadMoved);
+                }
+                None => all_removed = true,
+            }
+        }
+
+        for repair in exec.load_repairs()? {
+            let repaired = items.iter_mut().find(|item| match &item.source {
+                FileAndRecord { record, .. } | RecordOnly(record) => {
+                    record.id == repair.revision_id && record.filename == repair.filename
+                }
+                _ => false,
+            });
+
+            if let Some(item) = repaired {
+                item.repairs.push(repair);
+            }
+        }
+
+        for item in &mut items {
+            if let FileOnly(file) = &item.source {
+                item.findings = lint::lint(&file.contents, &settings.lint);
+            }
+        }
+
+        let mut review = Self::from_items(items);
+
+        if all_removed {
+            review.summary.chains_broken += 1;
+        }
+
+        Ok(review)
+    }
+
+    /// Reviews the revisions against those as of a git commit, eg. the base branch of
+    /// a pull request, as though the revisions of the commit had all been applied.
+    /// Problems then mean that revisions of the commit were changed, renamed, or
+    /// removed, or that new revisions do not come after them.
+    pub fn against_git_ref(settings: &RevisionsSettings, git_ref: &str) -> Result<Self> {
+        let base = RevisionsSettings {
+            source: RevisionSource::GitRef(git_ref.to_string()),
+            ..settings.clone()
+        };
+
+        let files = RevisionFile::all(settings)?;
+        let records = RevisionFile::all(&base)?
+            .into_iter()
+            .map(|file| RevisionRecord {
+                id: file.id,
+                applied_on: file.created_at,
+                down_checksum: file.down.map(|down| down.checksum),
+                checksum: file.checksum,
+                checksum_algorithm: file.checksum_algorithm,
+                created_at: file.created_at,
+                filename: file.filename,
+                name: file.name,
+                faked: false,
+                chain_hash: None,
+            })
+            .collect();
+
+        Ok(Self::from_items(ReviewItem::from_sources(files, records)))
+    }
+
+    fn from_items(items: Vec<ReviewItem>) -> Self {
+        use RevisionProblem::*;
 
-        let items = ReviewItem::from_sources(files, records);
         let mut summary = ReviewSummary::default();
 
         for item in &items {
+            if item.problems.contains(&BaselineIncomplete) {
+                summary.baselines_incomplete += 1;
+            }
+            if item.problems.contains(&ChainBroken) || item.problems.contains(&ChainHeadMoved) {
+                summary.chains_broken += 1;
+            }
             if item.problems.contains(&DuplicateId) {
                 summary.duplicate_ids += 1;
             }
@@ -265,11 +610,244 @@ impl Review {
             if item.problems.contains(&FileNotFound) {
                 summary.files_not_found += 1;
             }
+            if item.problems.contains(&FileRenamed) {
+                summary.files_renamed += 1;
+            }
+            if item.lint_failed() {
+                summary.lint_failed += 1;
+            }
             if item.problems.contains(&PrecedesApplied) {
                 summary.preceding_applied += 1;
             }
         }
 
-        Ok(Review { items, summary })
+        Review { items, summary }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::context::ChecksumAlgorithm;
+    use crate::revisions::BaselinedRevision;
+
+    fn file(id: i64, name: &str) -> RevisionFile {
+        let filename = format!("{:03}.1577836800.{}.sql", id, name);
+
+        RevisionFile {
+            id,
+            checksum: format!("checksum-{}", name),
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            contents: String::new(),
+            created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            path: PathBuf::from(&filename),
+            filename,
+            name: name.to_string(),
+            down: None,
+            baselined: Vec::new(),
+        }
+    }
+
+    fn record(file: &RevisionFile) -> RevisionRecord {
+        RevisionRecord {
+            id: file.id,
+            applied_on: file.created_at,
+            checksum: file.checksum.clone(),
+            checksum_algorithm: file.checksum_algorithm,
+            created_at: file.created_at,
+            filename: file.filename.clone(),
+            name: file.name.clone(),
+            down_checksum: None,
+            faked: false,
+            chain_hash: None,
+        }
+    }
+
+    fn baseline(id: i64, originals: &[&RevisionFile]) -> RevisionFile {
+        let mut baseline = file(id, "baseline");
+
+        baseline.baselined = originals
+            .iter()
+            .map(|original| BaselinedRevision {
+                filename: original.filename.clone(),
+                checksum_algorithm: original.checksum_algorithm,
+                checksum: original.checksum.clone(),
+            })
+            .collect();
+        baseline
+    }
+
+    #[test]
+    fn from_sources_matches_renamed_files_by_id_and_checksum() {
+        let (one, two) = (file(1, "one"), file(2, "two"));
+        let records = vec![record(&one), record(&two)];
+
+        let mut renamed = file(1, "won");
+        renamed.checksum = one.checksum.clone();
+
+        // Same id but different contents, so a new revision with a duplicate id
+        let replaced = file(2, "too");
+
+        let items = ReviewItem::from_sources(vec![renamed, replaced], records);
+        let renamed = items.iter().find(|item| item.name() == "won").unwrap();
+
+        assert!(renamed.applied());
+        assert_eq!(renamed.renamed_from(), Some(one.filename.as_str()));
+        assert_eq!(
+            renamed.problems(),
+            &HashSet::from([RevisionProblem::FileRenamed])
+        );
+
+        let replaced = items.iter().find(|item| item.name() == "too").unwrap();
+
+        assert!(replaced.pending());
+        assert!(items
+            .iter()
+            .any(|item| item.problems().contains(&RevisionProblem::FileNotFound)));
+    }
+
+    #[test]
+    fn against_git_ref_finds_revisions_changed_since_the_commit() {
+        let root = std::env::temp_dir().join(format!("jrny-review-git-{}", std::process::id()));
+        let dir = root.join("revisions");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(["-c", "user.name=jrny", "-c", "user.email=jrny@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(root.join("shared/fn.sql"), "create function f() ...;").unwrap();
+        fs::write(
+            dir.join("001.1577836800.one.sql"),
+            "-- jrny:include ../shared/fn.sql
+select 1;",
+        )
+        .unwrap();
+        fs::write(dir.join("003.1577836800.three.sql"), "select 3;").unwrap();
+
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "Add revisions"]);
+
+        fs::write(
+            dir.join("001.1577836800.one.sql"),
+            "-- jrny:include ../shared/fn.sql
+select 'one';",
+        )
+        .unwrap();
+        fs::rename(
+            dir.join("003.1577836800.three.sql"),
+            dir.join("003.1577836800.drei.sql"),
+        )
+        .unwrap();
+        fs::write(dir.join("002.1577836800.two.sql"), "select 2;").unwrap();
+
+        let settings = RevisionsSettings {
+            directory: dir.clone(),
+            checksum: ChecksumAlgorithm::default(),
+            filenames: Default::default(),
+            ids: Default::default(),
+            lint: Default::default(),
+            templates: Default::default(),
+            source: Default::default(),
+        };
+        let review = Review::against_git_ref(&settings, "HEAD");
+
+        fs::remove_dir_all(&root).unwrap();
+
+        let review = review.unwrap();
+        let problems = |name: &str| {
+            review
+                .items()
+                .iter()
+                .find(|item| item.name() == name)
+                .unwrap()
+                .problems()
+                .clone()
+        };
+
+        assert_eq!(
+            problems("one"),
+            HashSet::from([RevisionProblem::FileChanged])
+        );
+        assert_eq!(
+            problems("two"),
+            HashSet::from([RevisionProblem::PrecedesApplied])
+        );
+        assert_eq!(
+            problems("drei"),
+            HashSet::from([RevisionProblem::FileRenamed])
+        );
+    }
+
+    #[test]
+    fn renamed_from_ignores_compression() {
+        let one = file(1, "one");
+        let record = record(&one);
+
+        let mut compressed = file(1, "one");
+        compressed.filename = format!("{}.gz", one.filename);
+
+        let items = ReviewItem::from_sources(vec![compressed], vec![record]);
+
+        assert!(items[0].applied());
+        assert_eq!(items[0].renamed_from(), None);
+        assert!(items[0].problems().is_empty());
+    }
+
+    #[test]
+    fn from_sources_applies_baseline_for_baselined_records() {
+        let (one, two) = (file(1, "one"), file(2, "two"));
+        let records = vec![record(&one), record(&two)];
+        let items =
+            ReviewItem::from_sources(vec![baseline(2, &[&one, &two]), file(3, "three")], records);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].name(), "baseline");
+        assert_eq!(items[0].baselined(), 2);
+        assert!(items[0].applied());
+        assert!(items[0].problems().is_empty());
+        assert!(items[1].pending());
+        assert!(items[1].problems().is_empty());
+    }
+
+    #[test]
+    fn from_sources_leaves_baseline_pending_for_new_databases() {
+        let (one, two) = (file(1, "one"), file(2, "two"));
+        let items = ReviewItem::from_sources(vec![baseline(2, &[&one, &two])], Vec::new());
+
+        assert_eq!(items.len(), 1);
+        assert!(items[0].pending());
+        assert!(items[0].problems().is_empty());
+    }
+
+    #[test]
+    fn from_sources_reports_incomplete_or_changed_baselines() {
+        let (one, two) = (file(1, "one"), file(2, "two"));
+        let mut changed = record(&one);
+
+        changed.checksum = "something-else".to_string();
+
+        let items = ReviewItem::from_sources(vec![baseline(2, &[&one, &two])], vec![changed]);
+        let expected = HashSet::from([
+            RevisionProblem::BaselineIncomplete,
+            RevisionProblem::FileChanged,
+        ]);
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].problems(), &expected);
     }
 }
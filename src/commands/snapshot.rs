@@ -0,0 +1,169 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use similar::TextDiff;
+
+use super::diff::{create_table, grant_statement, terminated, view_kind};
+use crate::db::catalog::Catalog;
+use crate::Result;
+
+const HEADER: &str = "\
+-- Schema snapshot written by jrny after applying revisions - do not edit by hand.
+";
+
+/// Renders the catalog as SQL, with each object followed by its constraints, indexes,
+/// and grants and every kind of object sorted by name, so that the same schema
+/// always renders the same text.
+pub(super) fn render(catalog: &Catalog) -> String {
+    let mut sections = Vec::new();
+
+    // Qualified names and signatures cannot be mistaken for schema names
+    let grants = |object: &str| {
+        catalog
+            .grants
+            .iter()
+            .filter(|grant| grant.object == object)
+            .map(grant_statement)
+            .collect::<Vec<_>>()
+    };
+
+    for schema in &catalog.schemas {
+        let mut lines = vec![format!("CREATE SCHEMA {};", schema)];
+
+        lines.extend(grants(schema));
+        sections.push(lines);
+    }
+
+    for (table, columns) in &catalog.tables {
+        let mut lines = vec![create_table(table, columns)];
+
+        for ((_, name), constraint) in catalog
+            .constraints
+            .iter()
+            .filter(|(_, constraint)| &constraint.table == table)
+        {
+            lines.push(format!(
+                "ALTER TABLE {} ADD CONSTRAINT {} {};",
+                table, name, constraint.definition
+            ));
+        }
+
+        lines.extend(indexes(catalog, table));
+        lines.extend(grants(table));
+        sections.push(lines);
+    }
+
+    let mut views: Vec<_> = catalog.views.iter().collect();
+    views.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for view in views {
+        let mut lines = vec![format!(
+            "CREATE {} {} AS\n{}",
+            view_kind(view.materialized),
+            view.name,
+            terminated(&view.definition)
+        )];
+
+        lines.extend(indexes(catalog, &view.name));
+        lines.extend(grants(&view.name));
+        sections.push(lines);
+    }
+
+    for (signature, function) in &catalog.functions {
+        let mut lines = vec![format!("{};", function.definition.trim_end())];
+
+        lines.extend(grants(signature));
+        sections.push(lines);
+    }
+
+    let mut snapshot = HEADER.to_string();
+
+    for lines in sections {
+        snapshot.push('\n');
+        snapshot.push_str(&lines.join("\n"));
+        snapshot.push('\n');
+    }
+
+    snapshot
+}
+
+/// The statements creating the indexes on the table or materialized view.
+fn indexes(catalog: &Catalog, table: &str) -> Vec<String> {
+    catalog
+        .indexes
+        .values()
+        .filter(|index| index.table == table)
+        .map(|index| format!("{};", index.definition))
+        .collect()
+}
+
+/// The current contents of the snapshot file, which are empty if it does not exist yet.
+pub(super) fn read(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A unified diff of the committed snapshot against that of the database.
+pub(super) fn diff(committed: &str, current: &str) -> String {
+    TextDiff::from_lines(committed, current)
+        .unified_diff()
+        .header("committed", "database")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::{Column, Constraint, Grant};
+
+    #[test]
+    fn render_groups_objects_with_their_tables() {
+        let mut catalog = Catalog::default();
+
+        catalog.tables.insert(
+            "public.users".to_string(),
+            vec![Column {
+                name: "id".to_string(),
+                data_type: "integer".to_string(),
+                not_null: true,
+                default: None,
+                identity: Some("ALWAYS".to_string()),
+                generated: false,
+                serial: false,
+            }],
+        );
+        catalog.constraints.insert(
+            ("public.users".to_string(), "users_pkey".to_string()),
+            Constraint {
+                table: "public.users".to_string(),
+                foreign_key: false,
+                definition: "PRIMARY KEY (id)".to_string(),
+            },
+        );
+        catalog.grants.insert(Grant {
+            kind: "TABLE".to_string(),
+            object: "public.users".to_string(),
+            grantee: "reader".to_string(),
+            privilege: "SELECT".to_string(),
+        });
+        catalog.schemas.insert("app".to_string());
+
+        assert_eq!(
+            render(&catalog),
+            "-- Schema snapshot written by jrny after applying revisions - do not edit by hand.
+
+CREATE SCHEMA app;
+
+CREATE TABLE public.users (
+    id integer GENERATED ALWAYS AS IDENTITY NOT NULL
+);
+ALTER TABLE public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+GRANT SELECT ON TABLE public.users TO reader;
+"
+        );
+    }
+}
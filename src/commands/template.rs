@@ -0,0 +1,123 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::context::RevisionsSettings;
+use crate::revisions::replace_placeholders;
+use crate::{Error, Result};
+
+/// The name of the configured template used for new revisions unless another is chosen.
+pub(super) const DEFAULT_NAME: &str = "default";
+
+/// The template used for new revisions if no default template is configured.
+const BUILT_IN: &str = "-- Revision: {{ name }}
+--
+-- Add description here
+
+begin;
+
+-- Add SQL here
+
+commit;
+";
+
+/// Details of a new revision that can be substituted into its template.
+pub(super) struct TemplateValues<'a> {
+    pub id: &'a str,
+    pub created_at: DateTime<Utc>,
+    pub name: &'a str,
+    pub filename: &'a str,
+}
+
+/// Loads the configured template with the given name, or the default template
+/// (either configured or built-in) if no name is given.
+pub(super) fn load(settings: &RevisionsSettings, name: Option<&str>) -> Result<String> {
+    let path = match name {
+        Some(name) => Some(
+            settings
+                .templates
+                .get(name)
+                .ok_or_else(|| Error::TemplateNotFound(name.to_string()))?,
+        ),
+        None => settings.templates.get(DEFAULT_NAME),
+    };
+
+    match path {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => Ok(BUILT_IN.to_string()),
+    }
+}
+
+/// Substitutes the values of the new revision into the template's placeholders:
+///
+/// - `{{ name }}`, `{{ id }}`, and `{{ filename }}` of the revision
+/// - `{{ timestamp }}` of its creation, in RFC 3339 format
+/// - `{{ author }}`, from the git `user.name` or else the `$USER` running the command
+/// - `{{ env.NAME }}` for the value of any environment variable
+///
+/// Other placeholders are left for the variables substituted when the revision is applied.
+pub(super) fn render(template: &str, values: &TemplateValues) -> Result<String> {
+    let mut env_error = None;
+
+    let rendered = replace_placeholders(template, |placeholder| match placeholder {
+        "name" => Some(values.name.to_string()),
+        "id" => Some(values.id.to_string()),
+        "filename" => Some(values.filename.to_string()),
+        "timestamp" => Some(values.created_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "author" => Some(author()),
+        _ => {
+            let var = placeholder.strip_prefix("env.")?;
+
+            env::var(var)
+                .map_err(|err| env_error.get_or_insert(Error::BadEnvVar(err, var.to_string())))
+                .ok()
+        }
+    });
+
+    match env_error {
+        Some(err) => Err(err),
+        None => Ok(rendered),
+    }
+}
+
+/// The name of the person creating the revision.
+fn author() -> String {
+    Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| env::var("USER").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn render_substitutes_revision_values_and_leaves_others() {
+        let values = TemplateValues {
+            id: "007",
+            created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            name: "add-users",
+            filename: "007.1577836800.add-users.sql",
+        };
+
+        assert_eq!(
+            render(
+                "-- {{ filename }} ({{ id }}, {{ timestamp }})\ncreate table {{ schema }}.users ();",
+                &values
+            )
+            .unwrap(),
+            "-- 007.1577836800.add-users.sql (007, 2020-01-01T00:00:00Z)\ncreate table {{ schema }}.users ();"
+        );
+        assert!(render("-- {{ env.JRNY_TEST_UNDEFINED }}", &values).is_err());
+    }
+}
@@ -1,15 +1,38 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 use serde::Deserialize;
 
-use crate::{Error, Result};
+use super::{
+    ChecksumAlgorithm, Environment, FilenameScheme, IdStrategy, LintSettings, RevisionSource,
+};
+use crate::{Error, Result, LOCK, SIGNED};
 
 /// Configuration values specific to the revision files on disk.
 #[derive(Clone, Debug, Deserialize)]
 pub struct RevisionsSettings {
     /// The directory containing the SQL revision files
     pub directory: PathBuf,
+    /// The normalizations applied to revision contents before computing checksums
+    #[serde(default)]
+    pub checksum: ChecksumAlgorithm,
+    /// The format of revision filenames
+    #[serde(default)]
+    pub filenames: FilenameScheme,
+    /// How the ids of new revisions are chosen
+    #[serde(default)]
+    pub ids: IdStrategy,
+    /// The level of each lint rule checked against pending revisions
+    #[serde(default)]
+    pub lint: LintSettings,
+    /// Template files for new revisions by name, with any named `default`
+    /// used unless another is chosen
+    #[serde(default)]
+    pub templates: HashMap<String, PathBuf>,
+    /// Where the revision files are read from, which is chosen per command
+    #[serde(skip)]
+    pub source: RevisionSource,
 }
 
 /// Configuration values indicating the database table in which to store revision metadata.
@@ -21,12 +44,33 @@ pub struct TableSettings {
     pub name: String,
 }
 
+/// Configuration values for the schema snapshot written after applying revisions.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SnapshotSettings {
+    /// The file to write the snapshot to
+    pub path: PathBuf,
+    /// The database schemas to include, defaulting to all
+    #[serde(default)]
+    pub schemas: Vec<String>,
+}
+
 /// Project-specific settings that do not contain sensitive information and
 /// are likely to be consistent across environments.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub revisions: RevisionsSettings,
     pub table: TableSettings,
+    /// Where to write a snapshot of the schema after applying revisions, if anywhere
+    pub snapshot: Option<SnapshotSettings>,
+    /// Values substituted for `{{ name }}` placeholders in revision contents
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// The lockfile of revision checksums next to the config file
+    #[serde(skip)]
+    pub lockfile: PathBuf,
+    /// The signed manifest of revision checksums next to the config file
+    #[serde(skip)]
+    pub signed_manifest: PathBuf,
 }
 
 impl Config {
@@ -49,6 +93,25 @@ impl Config {
         // not the current working directory.
         config.revisions.directory = confpath.parent().unwrap().join(&config.revisions.directory);
 
+        // As are the templates and snapshot
+        for path in config.revisions.templates.values_mut() {
+            *path = confpath.parent().unwrap().join(&path);
+        }
+        if let Some(snapshot) = config.snapshot.as_mut() {
+            snapshot.path = confpath.parent().unwrap().join(&snapshot.path);
+        }
+        config.lockfile = confpath.parent().unwrap().join(LOCK);
+        config.signed_manifest = confpath.parent().unwrap().join(SIGNED);
+
         Ok(config)
     }
+
+    /// The variables to substitute into revision contents, with any values from
+    /// the environment taking precedence over those from the config.
+    pub fn variables_for(&self, env: &Environment) -> HashMap<String, String> {
+        let mut variables = self.variables.clone();
+
+        variables.extend(env.variables.clone());
+        variables
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -9,6 +10,20 @@ use crate::{Error, Result};
 #[derive(Clone, Debug, Deserialize)]
 pub struct DatabaseEnvironment {
     pub url: String,
+    /// Whether commands that discard records of applied revisions require
+    /// explicit confirmation, eg. for production databases
+    #[serde(default)]
+    pub protected: bool,
+}
+
+/// Environment values that restrict which revisions can be applied.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SigningEnvironment {
+    /// The hex-encoded public keys, as reported by `jrny sign`, whose signatures
+    /// are accepted - if any, only revisions in a manifest signed by one of them
+    /// can be applied
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
 }
 
 /// Project-specific settings that do contain sensitive information or
@@ -16,6 +31,12 @@ pub struct DatabaseEnvironment {
 #[derive(Clone, Debug, Deserialize)]
 pub struct Environment {
     pub database: DatabaseEnvironment,
+    /// Values substituted for `{{ name }}` placeholders in revision contents,
+    /// overriding any of the same name in the config
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub signing: SigningEnvironment,
 }
 
 impl Environment {
@@ -42,7 +63,10 @@ impl Environment {
         Self {
             database: DatabaseEnvironment {
                 url: url.to_owned(),
+                protected: false,
             },
+            variables: HashMap::new(),
+            signing: SigningEnvironment::default(),
         }
     }
 }
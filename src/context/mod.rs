@@ -3,5 +3,9 @@
 mod config;
 mod environment;
 
-pub use config::{Config, RevisionsSettings, TableSettings};
-pub use environment::{DatabaseEnvironment, Environment};
+pub use crate::checksum::ChecksumAlgorithm;
+pub use crate::filename::{FilenameScheme, IdStrategy, TimestampFormat};
+pub use crate::lint::{LintLevel, LintRule, LintSettings};
+pub use crate::revisions::RevisionSource;
+pub use config::{Config, RevisionsSettings, SnapshotSettings, TableSettings};
+pub use environment::{DatabaseEnvironment, Environment, SigningEnvironment};
@@ -0,0 +1,399 @@
+//! A snapshot of the objects in a database, read from the Postgres catalogs.
+use std::collections::{BTreeMap, BTreeSet};
+
+use postgres::types::ToSql;
+use postgres::Client;
+
+use crate::Result;
+
+/// Excludes system schemas and the objects of extensions, which are managed
+/// by the extensions themselves.
+const USER_OBJECTS: &str = "
+    n.nspname NOT IN ('pg_catalog', 'information_schema')
+    AND n.nspname NOT LIKE 'pg\\_%'
+    AND NOT EXISTS (
+        SELECT FROM pg_depend e
+        WHERE e.classid = '$$catalog$$'::regclass AND e.objid = $$oid$$ AND e.deptype = 'e'
+    )
+";
+
+/// Excludes the tables that track applied revisions, given by schema (`$1`)
+/// and table names (`$2`).
+const UNTRACKED: &str = "NOT (n.nspname = $1 AND c.relname = ANY($2))";
+
+const SELECT_SCHEMAS: &str = "
+SELECT quote_ident(n.nspname) AS name
+FROM pg_namespace n
+WHERE n.nspname NOT IN ('public', $1) AND $$user_schemas$$
+";
+
+const SELECT_TABLES: &str = "
+SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS name
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind = 'r' AND NOT c.relispartition AND $$user_relations$$ AND $$untracked$$
+";
+
+const SELECT_COLUMNS: &str = "
+SELECT
+    quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS table_name,
+    quote_ident(a.attname) AS name,
+    format_type(a.atttypid, a.atttypmod) AS data_type,
+    a.attnotnull AS not_null,
+    pg_get_expr(d.adbin, d.adrelid) AS default_value,
+    a.attidentity::text AS identity,
+    a.attgenerated::text AS generated,
+    EXISTS (
+        SELECT FROM pg_depend s
+        JOIN pg_class seq ON seq.oid = s.objid
+        WHERE s.refobjid = c.oid AND s.refobjsubid = a.attnum
+            AND s.deptype = 'a' AND seq.relkind = 'S'
+    ) AS serial
+FROM pg_attribute a
+JOIN pg_class c ON c.oid = a.attrelid
+JOIN pg_namespace n ON n.oid = c.relnamespace
+LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+WHERE c.relkind = 'r' AND NOT c.relispartition AND a.attnum > 0 AND NOT a.attisdropped
+    AND $$user_relations$$ AND $$untracked$$
+ORDER BY a.attnum
+";
+
+const SELECT_CONSTRAINTS: &str = "
+SELECT
+    quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS table_name,
+    quote_ident(con.conname) AS name,
+    con.contype = 'f' AS foreign_key,
+    pg_get_constraintdef(con.oid) AS definition
+FROM pg_constraint con
+JOIN pg_class c ON c.oid = con.conrelid
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind = 'r' AND con.contype IN ('p', 'u', 'f', 'c', 'x') AND con.conislocal
+    AND $$user_relations$$ AND $$untracked$$
+";
+
+/// Indexes that back constraints are created along with the constraints themselves.
+const SELECT_INDEXES: &str = "
+SELECT
+    quote_ident(n.nspname) || '.' || quote_ident(i.relname) AS name,
+    quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS table_name,
+    pg_get_indexdef(i.oid) AS definition
+FROM pg_index x
+JOIN pg_class i ON i.oid = x.indexrelid
+JOIN pg_class c ON c.oid = x.indrelid
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('r', 'm') AND NOT EXISTS (
+        SELECT FROM pg_constraint con
+        WHERE con.conindid = i.oid AND con.contype IN ('p', 'u', 'x')
+    )
+    AND $$user_relations$$ AND $$untracked$$
+";
+
+/// Views are ordered by creation, so that those depending on others come later.
+const SELECT_VIEWS: &str = "
+SELECT
+    quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS name,
+    c.relkind = 'm' AS materialized,
+    pg_get_viewdef(c.oid) AS definition
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('v', 'm') AND $$user_relations$$
+ORDER BY c.oid
+";
+
+const SELECT_FUNCTIONS: &str = "
+SELECT
+    quote_ident(n.nspname) || '.' || quote_ident(p.proname)
+        || '(' || pg_get_function_identity_arguments(p.oid) || ')' AS signature,
+    p.prokind = 'p' AS procedure,
+    pg_get_functiondef(p.oid) AS definition
+FROM pg_proc p
+JOIN pg_namespace n ON n.oid = p.pronamespace
+WHERE p.prokind IN ('f', 'p') AND $$user_functions$$
+";
+
+/// Privileges granted to the owners of objects are left out, since owners can
+/// differ between databases and always have them anyway.
+const SELECT_GRANTS: &str = "
+SELECT
+    'TABLE' AS kind,
+    quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS object,
+    $$grantee$$ AS grantee,
+    a.privilege_type AS privilege
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+CROSS JOIN aclexplode(COALESCE(c.relacl, acldefault('r', c.relowner))) a
+WHERE c.relkind IN ('r', 'v', 'm') AND NOT c.relispartition AND a.grantee <> c.relowner
+    AND $$user_relations$$ AND $$untracked$$
+UNION ALL
+SELECT
+    CASE WHEN p.prokind = 'p' THEN 'PROCEDURE' ELSE 'FUNCTION' END,
+    quote_ident(n.nspname) || '.' || quote_ident(p.proname)
+        || '(' || pg_get_function_identity_arguments(p.oid) || ')',
+    $$grantee$$,
+    a.privilege_type
+FROM pg_proc p
+JOIN pg_namespace n ON n.oid = p.pronamespace
+CROSS JOIN aclexplode(COALESCE(p.proacl, acldefault('f', p.proowner))) a
+WHERE p.prokind IN ('f', 'p') AND a.grantee <> p.proowner AND $$user_functions$$
+UNION ALL
+SELECT
+    'SCHEMA',
+    quote_ident(n.nspname),
+    $$grantee$$,
+    a.privilege_type
+FROM pg_namespace n
+CROSS JOIN aclexplode(COALESCE(n.nspacl, acldefault('n', n.nspowner))) a
+WHERE a.grantee <> n.nspowner AND $$user_schemas$$
+";
+
+const QUOTE_SCHEMAS: &str = "
+SELECT quote_ident(s) AS name FROM unnest($1::text[]) s
+";
+
+const GRANTEE: &str =
+    "CASE WHEN a.grantee = 0 THEN 'PUBLIC' ELSE quote_ident(pg_get_userbyid(a.grantee)) END";
+
+/// A column of a table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Column {
+    /// The quoted name of the column
+    pub name: String,
+    /// The type of the column, eg. `character varying(20)`
+    pub data_type: String,
+    pub not_null: bool,
+    /// The default expression, or the generation expression of a generated column
+    pub default: Option<String>,
+    /// Either `ALWAYS` or `BY DEFAULT` for identity columns
+    pub identity: Option<String>,
+    /// Whether the column is generated from its default expression
+    pub generated: bool,
+    /// Whether the column defaults to the next value of a sequence that it owns,
+    /// as created by the `serial` types
+    pub serial: bool,
+}
+
+/// A constraint on a table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Constraint {
+    /// The qualified name of the table
+    pub table: String,
+    pub foreign_key: bool,
+    /// The definition of the constraint, eg. `PRIMARY KEY (id)`
+    pub definition: String,
+}
+
+/// An index that does not back a constraint.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Index {
+    /// The qualified name of the table or materialized view
+    pub table: String,
+    /// The `CREATE INDEX` statement for the index
+    pub definition: String,
+}
+
+/// A view or materialized view.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct View {
+    /// The qualified name of the view
+    pub name: String,
+    pub materialized: bool,
+    /// The query of the view
+    pub definition: String,
+}
+
+/// A function or procedure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Function {
+    pub procedure: bool,
+    /// The `CREATE OR REPLACE` statement for the function
+    pub definition: String,
+}
+
+/// A privilege granted on an object.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Grant {
+    /// The kind of object as used by `GRANT`, eg. `TABLE` or `FUNCTION`
+    pub kind: String,
+    /// The qualified name (or signature) of the object
+    pub object: String,
+    /// The quoted name of the role, or `PUBLIC`
+    pub grantee: String,
+    /// The privilege, eg. `SELECT`
+    pub privilege: String,
+}
+
+/// The schemas, tables, columns, constraints, indexes, views, functions, and grants
+/// of a database, excluding the tables that track applied revisions. Objects are
+/// keyed by their quoted and (other than schemas) qualified names.
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    pub schemas: BTreeSet<String>,
+    /// The columns of each table, in order
+    pub tables: BTreeMap<String, Vec<Column>>,
+    /// Constraints keyed by table and constraint name
+    pub constraints: BTreeMap<(String, String), Constraint>,
+    pub indexes: BTreeMap<String, Index>,
+    /// Views in order of creation
+    pub views: Vec<View>,
+    /// Functions keyed by signature, eg. `public.add(integer, integer)`
+    pub functions: BTreeMap<String, Function>,
+    pub grants: BTreeSet<Grant>,
+}
+
+impl Catalog {
+    /// Loads the catalog of the connected database, excluding the given tables
+    /// within the given schema that track applied revisions. If any schemas are
+    /// given, only the objects within them are included.
+    pub fn load(
+        client: &mut Client,
+        tracking_schema: &str,
+        tracking_tables: &[String],
+        schemas: &[String],
+    ) -> Result<Self> {
+        let untracked: [&(dyn ToSql + Sync); 2] = [&tracking_schema, &tracking_tables];
+        let mut catalog = Self::default();
+
+        // Definitions then qualify every name, as they would otherwise depend on the search path,
+        // which is only changed within the transaction so that the connection can be reused
+        let mut tx = client.transaction()?;
+        tx.batch_execute("SET LOCAL search_path = ''")?;
+
+        for row in tx.query(&query(SELECT_SCHEMAS), &[&tracking_schema])? {
+            catalog.schemas.insert(row.get("name"));
+        }
+
+        for row in tx.query(&query(SELECT_TABLES), &untracked)? {
+            catalog.tables.insert(row.get("name"), Vec::new());
+        }
+
+        for row in tx.query(&query(SELECT_COLUMNS), &untracked)? {
+            let identity: &str = row.get("identity");
+            let generated: &str = row.get("generated");
+
+            catalog
+                .tables
+                .entry(row.get("table_name"))
+                .or_default()
+                .push(Column {
+                    name: row.get("name"),
+                    data_type: row.get("data_type"),
+                    not_null: row.get("not_null"),
+                    default: row.get("default_value"),
+                    identity: match identity {
+                        "a" => Some("ALWAYS".to_string()),
+                        "d" => Some("BY DEFAULT".to_string()),
+                        _ => None,
+                    },
+                    generated: generated == "s",
+                    serial: row.get("serial"),
+                });
+        }
+
+        for row in tx.query(&query(SELECT_CONSTRAINTS), &untracked)? {
+            let table: String = row.get("table_name");
+
+            catalog.constraints.insert(
+                (table.clone(), row.get("name")),
+                Constraint {
+                    table,
+                    foreign_key: row.get("foreign_key"),
+                    definition: row.get("definition"),
+                },
+            );
+        }
+
+        for row in tx.query(&query(SELECT_INDEXES), &untracked)? {
+            catalog.indexes.insert(
+                row.get("name"),
+                Index {
+                    table: row.get("table_name"),
+                    definition: row.get("definition"),
+                },
+            );
+        }
+
+        for row in tx.query(&query(SELECT_VIEWS), &[])? {
+            catalog.views.push(View {
+                name: row.get("name"),
+                materialized: row.get("materialized"),
+                definition: row.get("definition"),
+            });
+        }
+
+        for row in tx.query(&query(SELECT_FUNCTIONS), &[])? {
+            catalog.functions.insert(
+                row.get("signature"),
+                Function {
+                    procedure: row.get("procedure"),
+                    definition: row.get("definition"),
+                },
+            );
+        }
+
+        let select_grants = query(SELECT_GRANTS).replace("$$grantee$$", GRANTEE);
+
+        for row in tx.query(&select_grants, &untracked)? {
+            catalog.grants.insert(Grant {
+                kind: row.get("kind"),
+                object: row.get("object"),
+                grantee: row.get("grantee"),
+                privilege: row.get("privilege"),
+            });
+        }
+
+        if !schemas.is_empty() {
+            let quoted: Vec<String> = tx
+                .query(QUOTE_SCHEMAS, &[&schemas])?
+                .iter()
+                .map(|row| row.get("name"))
+                .collect();
+
+            catalog.retain_schemas(&quoted);
+        }
+
+        tx.commit()?;
+
+        Ok(catalog)
+    }
+
+    /// Keeps only the objects within the given (quoted) schemas.
+    fn retain_schemas(&mut self, schemas: &[String]) {
+        let within = |name: &str| {
+            schemas.iter().any(|schema| {
+                name.strip_prefix(schema.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+        };
+
+        self.schemas.retain(|schema| schemas.contains(schema));
+        self.tables.retain(|name, _| within(name));
+        self.constraints.retain(|(table, _), _| within(table));
+        self.indexes.retain(|name, _| within(name));
+        self.views.retain(|view| within(&view.name));
+        self.functions.retain(|signature, _| within(signature));
+        self.grants.retain(|grant| match grant.kind.as_str() {
+            "SCHEMA" => schemas.contains(&grant.object),
+            _ => within(&grant.object),
+        });
+    }
+
+    /// The view with the given qualified name, if any.
+    pub fn view(&self, name: &str) -> Option<&View> {
+        self.views.iter().find(|view| view.name == name)
+    }
+}
+
+/// Fills in the conditions shared by the catalog queries.
+fn query(template: &str) -> String {
+    let user_objects = |catalog: &str, oid: &str| {
+        USER_OBJECTS
+            .replace("$$catalog$$", catalog)
+            .replace("$$oid$$", oid)
+    };
+
+    template
+        .replace("$$user_relations$$", &user_objects("pg_class", "c.oid"))
+        .replace("$$user_functions$$", &user_objects("pg_proc", "p.oid"))
+        .replace("$$user_schemas$$", &user_objects("pg_namespace", "n.oid"))
+        .replace("$$untracked$$", UNTRACKED)
+}
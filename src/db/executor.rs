@@ -1,9 +1,15 @@
-use log::info;
-use postgres::Client;
+use std::collections::{HashMap, HashSet};
+
+use log::{info, warn};
+use postgres::{Client, GenericClient, Row, Transaction};
 
 use crate::context::{Config, Environment};
-use crate::revisions::{RevisionFile, RevisionRecord};
-use crate::Result;
+use crate::db::catalog::Catalog;
+use crate::db::client::connect_to;
+use crate::revisions::{
+    broken_links, RevisionDownFile, RevisionFile, RevisionRecord, RevisionRepair,
+};
+use crate::{Error, Result};
 
 const CREATE_SCHEMA: &str = "
 CREATE SCHEMA $$schema$$
@@ -11,7 +17,7 @@ CREATE SCHEMA $$schema$$
 
 const CREATE_TABLE: &str = "
 CREATE TABLE $$schema$$.$$table$$ (
-    id          INT          PRIMARY KEY,
+    id          BIGINT       PRIMARY KEY,
     created_at  TIMESTAMPTZ  NOT NULL,
     applied_on  TIMESTAMPTZ  NOT NULL,
     filename    TEXT         NOT NULL UNIQUE,
@@ -19,6 +25,94 @@ CREATE TABLE $$schema$$.$$table$$ (
     checksum    TEXT         NOT NULL
 )";
 
+/// Audit log of applied revisions that were intentionally changed, kept
+/// alongside the revisions table with the same name plus this suffix.
+const REPAIRS_SUFFIX: &str = "_repairs";
+
+const CREATE_REPAIRS_TABLE: &str = "
+CREATE TABLE $$schema$$.$$table$$ (
+    id                 SERIAL       PRIMARY KEY,
+    revision_id        BIGINT       NOT NULL,
+    filename           TEXT         NOT NULL,
+    previous_checksum  TEXT         NOT NULL,
+    checksum           TEXT         NOT NULL,
+    reason             TEXT         NOT NULL,
+    repaired_by        TEXT         NOT NULL,
+    repaired_on        TIMESTAMPTZ  NOT NULL
+)";
+
+/// The latest chain hash and number of rows as of the last change made by jrny, kept
+/// in a table with the name of the revisions table plus this suffix, so that removing
+/// the most recent rows (which leaves no row behind to show the gap) can be found.
+const HEAD_SUFFIX: &str = "_head";
+
+const CREATE_HEAD_TABLE: &str = "
+CREATE TABLE $$schema$$.$$head$$ (
+    one_row     BOOLEAN  PRIMARY KEY DEFAULT true CHECK (one_row),
+    chain_hash  TEXT,
+    revisions   BIGINT   NOT NULL
+)";
+
+/// Anchors the head when the table is created, trusting the rows as they are.
+const INSERT_HEAD: &str = "
+INSERT INTO $$schema$$.$$head$$ (chain_hash, revisions)
+SELECT
+    (SELECT chain_hash FROM $$schema$$.$$table$$ ORDER BY id DESC LIMIT 1),
+    (SELECT count(*) FROM $$schema$$.$$table$$)
+";
+
+/// The head as last recorded (if the row exists) alongside the head of the rows now.
+const SELECT_HEAD: &str = "
+SELECT
+    head.chain_hash AS recorded_hash,
+    head.revisions AS recorded_revisions,
+    (SELECT chain_hash FROM $$schema$$.$$table$$ ORDER BY id DESC LIMIT 1) AS chain_hash,
+    (SELECT count(*) FROM $$schema$$.$$table$$) AS revisions
+FROM (SELECT) AS now
+LEFT JOIN $$schema$$.$$head$$ AS head ON true
+";
+
+const UPDATE_HEAD: &str = "
+UPDATE $$schema$$.$$head$$
+SET
+    chain_hash = (SELECT chain_hash FROM $$schema$$.$$table$$ ORDER BY id DESC LIMIT 1),
+    revisions = (SELECT count(*) FROM $$schema$$.$$table$$)
+";
+
+/// Columns added to the table after its original definition, which are added
+/// to existing tables that predate them.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("down_checksum", "TEXT"),
+    // Existing rows were all hashed without any normalization
+    ("checksum_algorithm", "TEXT NOT NULL DEFAULT 'sha256'"),
+    ("faked", "BOOLEAN NOT NULL DEFAULT false"),
+    // Filled in for existing rows when added, so null only for rows inserted outside of jrny
+    (CHAIN_HASH, "TEXT"),
+];
+
+/// The column linking each row to the row before it by id, so that rows changed,
+/// removed, or inserted without updating the chain can be found.
+const CHAIN_HASH: &str = "chain_hash";
+
+const ALTER_COLUMN_TYPE: &str = "
+ALTER TABLE $$schema$$.$$table$$ ALTER COLUMN $$column$$ TYPE $$type$$
+";
+
+const COLUMN_TYPE: &str = "
+SELECT data_type FROM information_schema.columns
+WHERE table_schema = $1 AND table_name = $2 AND column_name = $3
+";
+
+const ADD_COLUMN: &str = "
+ALTER TABLE $$schema$$.$$table$$ ADD COLUMN $$column$$ $$definition$$
+";
+
+const COLUMN_EXISTS: &str = "
+SELECT EXISTS (
+    SELECT FROM information_schema.columns
+    WHERE table_schema = $1 AND table_name = $2 AND column_name = $3
+)";
+
 const TABLE_EXISTS: &str = "
 SELECT EXISTS (
    SELECT FROM pg_tables
@@ -31,14 +125,20 @@ SELECT EXISTS (
     WHERE schema_name = $1
 )";
 
+// Ids are cast to BIGINT throughout so that tables still using INTs can be read
+// and written until they are upgraded
 const SELECT_REVISIONS: &str = "
 SELECT
-    id,
+    id::BIGINT AS id,
     applied_on,
     checksum,
     created_at,
     filename,
-    name
+    name,
+    down_checksum,
+    checksum_algorithm,
+    faked,
+    chain_hash
 FROM $$schema$$.$$table$$
 ORDER BY id ASC
 ";
@@ -50,68 +150,260 @@ INSERT INTO $$schema$$.$$table$$ (
     created_at,
     checksum,
     filename,
-    name
-) VALUES (clock_timestamp(), $1, $2, $3, $4, $5)
+    name,
+    down_checksum,
+    checksum_algorithm,
+    faked
+) VALUES (clock_timestamp(), $1::BIGINT, $2, $3, $4, $5, $6, $7, $8)
+RETURNING
+    id::BIGINT AS id,
+    applied_on,
+    checksum,
+    created_at,
+    filename,
+    name,
+    down_checksum,
+    checksum_algorithm,
+    faked,
+    chain_hash
 ";
 
+/// The chain hash of the last row before the given id, and whether any row comes
+/// after it.
+const SELECT_NEIGHBOURS: &str = "
+SELECT
+    (
+        SELECT chain_hash FROM $$schema$$.$$table$$
+        WHERE id < $1::BIGINT
+        ORDER BY id DESC
+        LIMIT 1
+    ) AS previous_hash,
+    EXISTS (
+        SELECT FROM $$schema$$.$$table$$ WHERE id > $1::BIGINT
+    ) AS followed
+";
+
+const UPDATE_CHAIN_HASH: &str = "
+UPDATE $$schema$$.$$table$$
+SET chain_hash = $2
+WHERE id = $1::BIGINT
+";
+
+const RENAME_REVISION: &str = "
+UPDATE $$schema$$.$$table$$
+SET filename = $3, name = $4, created_at = $5
+WHERE id = $1::BIGINT AND filename = $2
+";
+
+const REPAIR_REVISION: &str = "
+UPDATE $$schema$$.$$table$$
+SET checksum = $3
+WHERE id = $1::BIGINT AND filename = $2
+";
+
+const INSERT_REPAIR: &str = "
+INSERT INTO $$schema$$.$$repairs$$ (
+    repaired_on,
+    revision_id,
+    filename,
+    previous_checksum,
+    checksum,
+    reason,
+    repaired_by
+) VALUES (clock_timestamp(), $1::BIGINT, $2, $3, $4, $5, COALESCE($6, session_user))
+";
+
+const SELECT_REPAIRS: &str = "
+SELECT
+    revision_id::BIGINT AS revision_id,
+    filename,
+    reason,
+    repaired_by,
+    repaired_on
+FROM $$schema$$.$$repairs$$
+ORDER BY repaired_on ASC
+";
+
+const RENAME_REPAIRS: &str = "
+UPDATE $$schema$$.$$repairs$$
+SET filename = $3
+WHERE revision_id = $1::BIGINT AND filename = $2
+";
+
+const DELETE_REVISION: &str = "
+DELETE FROM $$schema$$.$$table$$
+WHERE id = $1::BIGINT AND filename = $2
+";
+
+/// The names of the tables that track applied revisions, within the configured schema.
+pub fn tracking_tables(config: &Config) -> Vec<String> {
+    vec![
+        config.table.name.clone(),
+        format!("{}{}", config.table.name, REPAIRS_SUFFIX),
+        format!("{}{}", config.table.name, HEAD_SUFFIX),
+    ]
+}
+
+/// The sequences owned by the tables that track applied revisions, which tools
+/// like `pg_dump` treat separately from the tables.
+pub fn tracking_sequences(config: &Config) -> Vec<String> {
+    vec![format!("{}{}_id_seq", config.table.name, REPAIRS_SUFFIX)]
+}
+
 pub struct Executor {
     client: Client,
     schema: String,
     table: String,
+    variables: HashMap<String, String>,
 }
 
 impl Executor {
     pub fn new(config: &Config, env: &Environment) -> Result<Self> {
         let client = Client::try_from(env)?;
 
-        Ok(Self {
+        Ok(Self::with_client(client, config, env))
+    }
+
+    /// Connects to the named database on the server specified by the environment,
+    /// rather than to the database specified by the environment itself.
+    pub fn for_database(config: &Config, env: &Environment, dbname: &str) -> Result<Self> {
+        let client = connect_to(env, dbname)?;
+
+        Ok(Self::with_client(client, config, env))
+    }
+
+    fn with_client(client: Client, config: &Config, env: &Environment) -> Self {
+        Self {
             client,
             schema: config.table.schema.clone(),
             table: config.table.name.clone(),
-        })
+            variables: config.variables_for(env),
+        }
     }
 
     pub fn ensure_table_exists(&mut self) -> Result<()> {
         if !self.schema_exists()? {
             self.create_schema()?;
         }
-        if !self.table_exists()? {
+        if !self.table_exists(&self.table.clone())? {
             self.create_table()?;
         }
+        if !self.table_exists(&self.repairs_table())? {
+            self.create_repairs_table()?;
+        }
+        for (column, definition) in ADDED_COLUMNS {
+            if !self.column_exists(column)? {
+                self.add_column(column, definition)?;
+            }
+        }
+        // Only once the chain hash column exists, since the head is anchored to it
+        if !self.table_exists(&self.head_table())? {
+            self.create_head_table()?;
+        }
+
+        if !self.narrow_id_columns()?.is_empty() {
+            warn!(
+                "Revision ids in {}.{} are INTs, which cannot hold timestamp ids - run `jrny upgrade` to widen them",
+                self.schema, self.table
+            );
+        }
 
         Ok(())
     }
 
+    /// Widens the revision id columns of tables created when ids were INTs, which
+    /// timestamp-derived ids do not fit in. This rewrites the tables while holding
+    /// an exclusive lock on them, so it is only done when applying revisions or
+    /// when asked to, and never by commands that only read.
+    pub fn upgrade_table(&mut self) -> Result<bool> {
+        let narrow = self.narrow_id_columns()?;
+
+        for (table, column) in &narrow {
+            warn!(
+                "Rewriting table {}.{} to widen column {}, which locks it until done",
+                self.schema, table, column
+            );
+            self.alter_column_type(table, column, "BIGINT")?;
+        }
+
+        Ok(!narrow.is_empty())
+    }
+
+    /// The revision id columns that are still INTs, in tables that exist.
+    fn narrow_id_columns(&mut self) -> Result<Vec<(String, &'static str)>> {
+        let mut narrow = Vec::new();
+
+        for (table, column) in [
+            (self.table.clone(), "id"),
+            (self.repairs_table(), "revision_id"),
+        ] {
+            if self.column_type(&table, column)?.as_deref() == Some("integer") {
+                narrow.push((table, column));
+            }
+        }
+
+        Ok(narrow)
+    }
+
     pub fn load_revisions(&mut self) -> Result<Vec<RevisionRecord>> {
-        let stmt = SELECT_REVISIONS
+        let (select, _) = self.chain_statements();
+
+        load_records(&mut self.client, &select)
+    }
+
+    pub fn load_repairs(&mut self) -> Result<Vec<RevisionRepair>> {
+        let stmt = SELECT_REPAIRS
             .replace("$$schema$$", &self.schema)
-            .replace("$$table$$", &self.table);
+            .replace("$$repairs$$", &self.repairs_table());
 
         let rows = self.client.query(stmt.as_str(), &[])?;
 
-        let revisions = rows
+        Ok(rows
             .iter()
-            .map(|r| RevisionRecord {
-                id: r.get("id"),
-                applied_on: r.get("applied_on"),
-                created_at: r.get("created_at"),
-                checksum: r.get("checksum"),
+            .map(|r| RevisionRepair {
+                revision_id: r.get("revision_id"),
                 filename: r.get("filename"),
-                name: r.get("name"),
+                reason: r.get("reason"),
+                repaired_by: r.get("repaired_by"),
+                repaired_on: r.get("repaired_on"),
             })
-            .collect();
-
-        Ok(revisions)
+            .collect())
     }
 
     pub fn run_revision(&mut self, revision: &RevisionFile) -> Result<()> {
-        let insert_revision = INSERT_REVISION
-            .replace("$$schema$$", &self.schema)
-            .replace("$$table$$", &self.table);
+        self.client
+            .batch_execute(&revision.render(&self.variables)?)?;
+
+        self.insert_revision(revision, false)
+    }
+
+    /// Records the revision as applied without running it, eg. when it was applied by hand.
+    pub fn fake_revision(&mut self, revision: &RevisionFile) -> Result<()> {
+        self.insert_revision(revision, true)
+    }
+
+    fn insert_revision(&mut self, revision: &RevisionFile, faked: bool) -> Result<()> {
+        let [insert_revision, select_neighbours] =
+            [INSERT_REVISION, SELECT_NEIGHBOURS].map(|stmt| {
+                stmt.replace("$$schema$$", &self.schema)
+                    .replace("$$table$$", &self.table)
+            });
+        let (select, update) = self.chain_statements();
+        let (select_head, update_head) = self.head_statements();
 
-        self.client.batch_execute(&revision.contents)?;
+        let mut tx = self.client.transaction()?;
+        let head_intact = head_intact(&mut tx, &select_head)?;
+        let neighbours = tx.query_one(select_neighbours.as_str(), &[&revision.id])?;
+        let previous_hash: Option<String> = neighbours.get("previous_hash");
 
-        let _ = self.client.execute(
+        // Revisions are almost always applied after all others, so only the new record
+        // needs linking, without loading every record as relinking would
+        let intact = match neighbours.get("followed") {
+            true => Some(intact_records(&mut tx, &select)?),
+            false => None,
+        };
+
+        let row = tx.query_one(
             insert_revision.as_str(),
             &[
                 &revision.id,
@@ -119,16 +411,240 @@ impl Executor {
                 &revision.checksum,
                 &revision.filename,
                 &revision.name,
+                &revision.down.as_ref().map(|down| &down.checksum),
+                &revision.checksum_algorithm.to_string(),
+                &faked,
+            ],
+        )?;
+
+        match intact {
+            Some(mut intact) => {
+                intact.insert(revision.id);
+                relink(&mut tx, &select, &update, &intact, revision.id)?;
+            }
+            None => {
+                let record = record_from_row(&row)?;
+                let hash = record.chain_hash_after(previous_hash.as_deref());
+
+                tx.execute(update.as_str(), &[&record.id, &hash])?;
+            }
+        }
+        if head_intact {
+            tx.execute(update_head.as_str(), &[])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Runs the down file and removes the record of the revision, as recorded rather
+    /// than as its file is now named (eg. after being compressed), within the same
+    /// transaction.
+    pub fn revert_revision(
+        &mut self,
+        record: &RevisionRecord,
+        down: &RevisionDownFile,
+    ) -> Result<()> {
+        let delete_revision = DELETE_REVISION
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &self.table);
+
+        let (select, update) = self.chain_statements();
+        let (select_head, update_head) = self.head_statements();
+        let down_sql = down.render(&self.variables)?;
+
+        let mut tx = self.client.transaction()?;
+        let intact = intact_records(&mut tx, &select)?;
+        let head_intact = head_intact(&mut tx, &select_head)?;
+
+        tx.batch_execute(&down_sql)?;
+        let deleted = tx.execute(delete_revision.as_str(), &[&record.id, &record.filename])?;
+
+        if deleted == 0 {
+            return Err(Error::RevisionRecordNotFound(record.filename.clone()));
+        }
+
+        relink(&mut tx, &select, &update, &intact, record.id)?;
+        if head_intact {
+            tx.execute(update_head.as_str(), &[])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Updates the filename (and the name and timestamp within it) recorded for an
+    /// applied revision to those of its renamed file.
+    pub fn rename_revision(&mut self, record: &RevisionRecord, file: &RevisionFile) -> Result<()> {
+        let rename_revision = RENAME_REVISION
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &self.table);
+        let rename_repairs = RENAME_REPAIRS
+            .replace("$$schema$$", &self.schema)
+            .replace("$$repairs$$", &self.repairs_table());
+        let (select, update) = self.chain_statements();
+        let (select_head, update_head) = self.head_statements();
+
+        let mut tx = self.client.transaction()?;
+        let intact = intact_records(&mut tx, &select)?;
+        let head_intact = head_intact(&mut tx, &select_head)?;
+
+        let _ = tx.execute(
+            rename_revision.as_str(),
+            &[
+                &record.id,
+                &record.filename,
+                &file.filename,
+                &file.name,
+                &file.created_at,
+            ],
+        )?;
+        let _ = tx.execute(
+            rename_repairs.as_str(),
+            &[&record.id, &record.filename, &file.filename],
+        )?;
+
+        relink(&mut tx, &select, &update, &intact, record.id)?;
+        if head_intact {
+            tx.execute(update_head.as_str(), &[])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Whether the latest record and the number of records are as jrny last left them,
+    /// ie. that no record after the latest was removed outside of jrny.
+    pub fn head_intact(&mut self) -> Result<bool> {
+        let (select_head, _) = self.head_statements();
+
+        head_intact(&mut self.client, &select_head)
+    }
+
+    /// Loads the catalog of the database, excluding the tables that track applied revisions.
+    /// If any schemas are given, only the objects within them are included.
+    pub fn catalog(&mut self, schemas: &[String]) -> Result<Catalog> {
+        let tracking_tables = [self.table.clone(), self.repairs_table(), self.head_table()];
+
+        Catalog::load(&mut self.client, &self.schema, &tracking_tables, schemas)
+    }
+
+    fn repairs_table(&self) -> String {
+        format!("{}{}", self.table, REPAIRS_SUFFIX)
+    }
+
+    fn head_table(&self) -> String {
+        format!("{}{}", self.table, HEAD_SUFFIX)
+    }
+
+    /// The statements selecting the records and updating the chain hash of one.
+    fn chain_statements(&self) -> (String, String) {
+        let [select, update] = [SELECT_REVISIONS, UPDATE_CHAIN_HASH].map(|stmt| {
+            stmt.replace("$$schema$$", &self.schema)
+                .replace("$$table$$", &self.table)
+        });
+
+        (select, update)
+    }
+
+    /// The statements selecting the head of the chain and moving it to the latest record.
+    fn head_statements(&self) -> (String, String) {
+        let [select, update] = [SELECT_HEAD, UPDATE_HEAD].map(|stmt| {
+            stmt.replace("$$schema$$", &self.schema)
+                .replace("$$table$$", &self.table)
+                .replace("$$head$$", &self.head_table())
+        });
+
+        (select, update)
+    }
+
+    /// Updates the checksum recorded for an applied revision to the given checksum,
+    /// recording the previous checksum along with the reason and who changed it.
+    pub fn repair_revision(
+        &mut self,
+        record: &RevisionRecord,
+        checksum: &str,
+        reason: &str,
+        repaired_by: Option<&str>,
+    ) -> Result<()> {
+        let repair_revision = REPAIR_REVISION
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &self.table);
+        let insert_repair = INSERT_REPAIR
+            .replace("$$schema$$", &self.schema)
+            .replace("$$repairs$$", &self.repairs_table());
+        let (select, update) = self.chain_statements();
+        let (_, update_head) = self.head_statements();
+
+        let mut tx = self.client.transaction()?;
+        let mut intact = intact_records(&mut tx, &select)?;
+
+        let _ = tx.execute(
+            repair_revision.as_str(),
+            &[&record.id, &record.filename, &checksum],
+        )?;
+        let _ = tx.execute(
+            insert_repair.as_str(),
+            &[
+                &record.id,
+                &record.filename,
+                &record.checksum,
+                &checksum,
+                &reason,
+                &repaired_by,
             ],
         )?;
 
+        // The repaired record is relinked even if it had been changed outside of jrny,
+        // and the head moved to the latest record even if later records had been removed,
+        // since repairing it accepts the current values
+        intact.insert(record.id);
+        relink(&mut tx, &select, &update, &intact, record.id)?;
+        tx.execute(update_head.as_str(), &[])?;
+        tx.commit()?;
+
         Ok(())
     }
 
-    fn table_exists(&mut self) -> Result<bool> {
+    /// Removes the record of an applied revision, first running the given cleanup SQL
+    /// (if any) within the same transaction.
+    pub fn forget_revision(
+        &mut self,
+        record: &RevisionRecord,
+        cleanup: Option<&str>,
+    ) -> Result<()> {
+        let delete_revision = DELETE_REVISION
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &self.table);
+        let (select, update) = self.chain_statements();
+        let (select_head, update_head) = self.head_statements();
+
+        let mut tx = self.client.transaction()?;
+        let intact = intact_records(&mut tx, &select)?;
+        let head_intact = head_intact(&mut tx, &select_head)?;
+
+        if let Some(cleanup) = cleanup {
+            tx.batch_execute(cleanup)?;
+        }
+        let deleted = tx.execute(delete_revision.as_str(), &[&record.id, &record.filename])?;
+
+        if deleted == 0 {
+            return Err(Error::RevisionRecordNotFound(record.filename.clone()));
+        }
+
+        relink(&mut tx, &select, &update, &intact, record.id)?;
+        if head_intact {
+            tx.execute(update_head.as_str(), &[])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn table_exists(&mut self, table: &str) -> Result<bool> {
         let row = self
             .client
-            .query_one(TABLE_EXISTS, &[&self.schema, &self.table])?;
+            .query_one(TABLE_EXISTS, &[&self.schema, &table])?;
 
         Ok(row.get("exists"))
     }
@@ -139,6 +655,22 @@ impl Executor {
         Ok(row.get("exists"))
     }
 
+    fn column_exists(&mut self, column: &str) -> Result<bool> {
+        let row = self
+            .client
+            .query_one(COLUMN_EXISTS, &[&self.schema, &self.table, &column])?;
+
+        Ok(row.get("exists"))
+    }
+
+    fn column_type(&mut self, table: &str, column: &str) -> Result<Option<String>> {
+        let row = self
+            .client
+            .query_opt(COLUMN_TYPE, &[&self.schema, &table, &column])?;
+
+        Ok(row.map(|row| row.get("data_type")))
+    }
+
     fn create_schema(&mut self) -> Result<()> {
         info!("Creating schema {}", self.schema);
         let create = CREATE_SCHEMA.replace("$$schema$$", &self.schema);
@@ -158,4 +690,310 @@ impl Executor {
 
         Ok(())
     }
+
+    fn create_repairs_table(&mut self) -> Result<()> {
+        let repairs_table = self.repairs_table();
+
+        info!("Creating table {}.{}", self.schema, repairs_table);
+        let create = CREATE_REPAIRS_TABLE
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &repairs_table);
+
+        self.client.execute(create.as_str(), &[])?;
+
+        Ok(())
+    }
+
+    fn create_head_table(&mut self) -> Result<()> {
+        let head_table = self.head_table();
+
+        info!("Creating table {}.{}", self.schema, head_table);
+        let [create, insert] = [CREATE_HEAD_TABLE, INSERT_HEAD].map(|stmt| {
+            stmt.replace("$$schema$$", &self.schema)
+                .replace("$$table$$", &self.table)
+                .replace("$$head$$", &head_table)
+        });
+
+        // Anchored in the same transaction, so that the table is never left without its row
+        let mut tx = self.client.transaction()?;
+
+        tx.execute(create.as_str(), &[])?;
+        tx.execute(insert.as_str(), &[])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn alter_column_type(&mut self, table: &str, column: &str, new_type: &str) -> Result<()> {
+        info!(
+            "Changing type of column {} in table {}.{} to {}",
+            column, self.schema, table, new_type
+        );
+        let alter = ALTER_COLUMN_TYPE
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", table)
+            .replace("$$column$$", column)
+            .replace("$$type$$", new_type);
+
+        self.client.execute(alter.as_str(), &[])?;
+
+        Ok(())
+    }
+
+    fn add_column(&mut self, column: &str, definition: &str) -> Result<()> {
+        info!(
+            "Adding column {} to table {}.{}",
+            column, self.schema, self.table
+        );
+        let add = ADD_COLUMN
+            .replace("$$schema$$", &self.schema)
+            .replace("$$table$$", &self.table)
+            .replace("$$column$$", column)
+            .replace("$$definition$$", definition);
+        let (select, update) = self.chain_statements();
+
+        let mut tx = self.client.transaction()?;
+
+        tx.execute(add.as_str(), &[])?;
+
+        // Existing records are linked in the same transaction, so that the column
+        // is never left added without them having been linked
+        if column == CHAIN_HASH {
+            link_existing_records(&mut tx, &select, &update)?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+fn load_records<C: GenericClient>(client: &mut C, select: &str) -> Result<Vec<RevisionRecord>> {
+    client
+        .query(select, &[])?
+        .iter()
+        .map(record_from_row)
+        .collect()
+}
+
+fn record_from_row(r: &Row) -> Result<RevisionRecord> {
+    Ok(RevisionRecord {
+        id: r.get("id"),
+        applied_on: r.get("applied_on"),
+        created_at: r.get("created_at"),
+        checksum: r.get("checksum"),
+        checksum_algorithm: r.get::<_, &str>("checksum_algorithm").parse()?,
+        filename: r.get("filename"),
+        name: r.get("name"),
+        down_checksum: r.get("down_checksum"),
+        faked: r.get("faked"),
+        chain_hash: r.get("chain_hash"),
+    })
+}
+
+/// Links all records into a new chain, trusting them as they are, when the chain
+/// hash column is first added.
+fn link_existing_records(tx: &mut Transaction, select: &str, update: &str) -> Result<()> {
+    let records = load_records(tx, select)?;
+    let all = records.iter().map(|record| record.id).collect();
+
+    info!("Linking {} existing revision(s)", records.len());
+    relink(tx, select, update, &all, i64::MIN)
+}
+
+/// The ids of the records whose links in the chain are intact, loaded before
+/// changing any records so that they can be relinked afterwards.
+fn intact_records(tx: &mut Transaction, select: &str) -> Result<HashSet<i64>> {
+    let records = load_records(tx, select)?;
+    let broken = broken_links(&records);
+
+    Ok(records
+        .iter()
+        .map(|record| record.id)
+        .filter(|id| !broken.contains(id))
+        .collect())
+}
+
+/// Whether the head of the chain recorded by jrny matches the latest record and the
+/// number of records, checked before changing any records so that the head is only
+/// moved along with them if so. A missing head row only matches if there are no records.
+fn head_intact<C: GenericClient>(client: &mut C, select_head: &str) -> Result<bool> {
+    let row = client.query_one(select_head, &[])?;
+    let recorded_hash: Option<String> = row.get("recorded_hash");
+    let recorded_revisions: Option<i64> = row.get("recorded_revisions");
+    let chain_hash: Option<String> = row.get("chain_hash");
+    let revisions: i64 = row.get("revisions");
+
+    Ok(recorded_hash == chain_hash && recorded_revisions.unwrap_or(0) == revisions)
+}
+
+/// Updates the chain hashes after jrny changes, inserts, or removes the record with
+/// the given id: that record and each record after it is linked to the record before
+/// it if its link was intact, so that a change made outside of jrny is never hidden
+/// by relinking it.
+fn relink(
+    tx: &mut Transaction,
+    select: &str,
+    update: &str,
+    intact: &HashSet<i64>,
+    from_id: i64,
+) -> Result<()> {
+    let mut previous: Option<String> = None;
+
+    for record in load_records(tx, select)? {
+        let hash = if record.id >= from_id && intact.contains(&record.id) {
+            let hash = record.chain_hash_after(previous.as_deref());
+
+            if record.chain_hash.as_ref() != Some(&hash) {
+                tx.execute(update, &[&record.id, &hash])?;
+            }
+            Some(hash)
+        } else {
+            record.chain_hash
+        };
+
+        previous = hash;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revisions::revision_file;
+
+    /// Connects to the database at `JRNY_TEST_DATABASE_URL` to track revisions in the
+    /// given table, or returns nothing if it is not set so that the test is skipped.
+    fn test_executor(table: &str) -> Option<Executor> {
+        let url = std::env::var("JRNY_TEST_DATABASE_URL").ok()?;
+        let cfg: Config = toml::from_str(&format!(
+            "[revisions]\ndirectory = \"revisions\"\n[table]\nschema = \"public\"\nname = \"{}\"\n",
+            table
+        ))
+        .unwrap();
+
+        Some(Executor::new(&cfg, &Environment::from_database_url(&url)).unwrap())
+    }
+
+    #[test]
+    fn ensure_table_exists_upgrades_a_table_from_before_the_added_columns() {
+        let table = format!("jrny_test_upgrade_{}", std::process::id());
+        let Some(mut exec) = test_executor(&table) else {
+            return;
+        };
+
+        exec.client
+            .batch_execute(&format!(
+                "
+                DROP TABLE IF EXISTS {table}, {table}_repairs, {table}_head;
+                CREATE TABLE {table} (
+                    id          INT          PRIMARY KEY,
+                    created_at  TIMESTAMPTZ  NOT NULL,
+                    applied_on  TIMESTAMPTZ  NOT NULL,
+                    filename    TEXT         NOT NULL UNIQUE,
+                    name        TEXT         NOT NULL,
+                    checksum    TEXT         NOT NULL
+                );
+                INSERT INTO {table} VALUES
+                    (1, now(), now(), '001.1577836800.a.sql', 'a', 'checksum-a'),
+                    (4, now(), now(), '004.1577836800.d.sql', 'd', 'checksum-d');
+                "
+            ))
+            .unwrap();
+
+        let result = (|| {
+            // Readable before the ids are widened, with the existing records linked
+            exec.ensure_table_exists()?;
+
+            let linked = exec.load_revisions()?;
+
+            assert!(exec.upgrade_table()?);
+            assert!(!exec.upgrade_table()?);
+
+            // Inserted both before and after the existing records
+            exec.fake_revision(&revision_file(2, "b", "select 2;"))?;
+            exec.fake_revision(&revision_file(5, "e", "select 5;"))?;
+
+            Ok::<_, Error>((linked, exec.load_revisions()?))
+        })();
+
+        exec.client
+            .batch_execute(&format!(
+                "DROP TABLE {table}, {table}_repairs, {table}_head;"
+            ))
+            .unwrap();
+
+        let (linked, records) = result.unwrap();
+
+        assert_eq!(linked.len(), 2);
+        assert!(linked.iter().all(|record| record.chain_hash.is_some()));
+        assert_eq!(
+            records.iter().map(|record| record.id).collect::<Vec<_>>(),
+            vec![1, 2, 4, 5]
+        );
+        assert!(broken_links(&records).is_empty());
+    }
+
+    #[test]
+    fn catalog_leaves_the_search_path_of_the_connection_unchanged() {
+        let Some(mut exec) = test_executor("jrny_test_catalog") else {
+            return;
+        };
+
+        let search_path = |exec: &mut Executor| -> String {
+            exec.client
+                .query_one("SHOW search_path", &[])
+                .unwrap()
+                .get(0)
+        };
+        let before = search_path(&mut exec);
+
+        exec.catalog(&[]).unwrap();
+
+        assert_eq!(search_path(&mut exec), before);
+    }
+
+    #[test]
+    fn head_shows_the_latest_records_were_removed_until_repaired() {
+        let table = format!("jrny_test_head_{}", std::process::id());
+        let Some(mut exec) = test_executor(&table) else {
+            return;
+        };
+
+        let result = (|| {
+            exec.ensure_table_exists()?;
+            exec.fake_revision(&revision_file(1, "a", "select 1;"))?;
+            exec.fake_revision(&revision_file(2, "b", "select 2;"))?;
+
+            let intact_after_applying = exec.head_intact()?;
+
+            exec.client
+                .execute(&format!("DELETE FROM {table} WHERE id = 2"), &[])?;
+
+            let intact_after_removing = exec.head_intact()?;
+
+            // Later changes leave the head as it was, so the removal stays evident
+            exec.fake_revision(&revision_file(3, "c", "select 3;"))?;
+
+            let intact_after_changing = exec.head_intact()?;
+
+            let records = exec.load_revisions()?;
+            exec.repair_revision(&records[1], &records[1].checksum, "Removed 2", None)?;
+
+            Ok::<_, Error>([
+                intact_after_applying,
+                intact_after_removing,
+                intact_after_changing,
+                exec.head_intact()?,
+            ])
+        })();
+
+        exec.client
+            .batch_execute(&format!(
+                "DROP TABLE {table}, {table}_repairs, {table}_head;"
+            ))
+            .unwrap();
+
+        assert_eq!(result.unwrap(), [true, false, false, true]);
+    }
 }
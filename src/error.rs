@@ -13,22 +13,61 @@ pub enum Error {
     // TODO This has gotten a bit unwieldy.
     // Should these just be individual structs now to avoid
     // big matches anywhere, or module-leel enums?
+    ArchiveInvalid(String, String),
     BadEnvVar(env::VarError, String),
+    BaselineDumpFailed(String),
+    BaselineStateMismatch(i64),
+    ChecksumAlgorithmUnknown(String),
     ConfigNotFound(String),
     DatabaseError(postgres::Error),
+    EditorFailed(String),
     EnvNotFound,
+    EnvProtected,
+    FileNotFound(String),
     FileNotValid(String),
+    GitFailed(String),
+    GitRefNotFound(String),
     IoError(io::Error),
+    LintRuleUnknown(String),
+    LockfileNotFound(String),
     PathAlreadyExists(String),
     PathInvalid(String),
     PathNotDirectory(String),
     PathNotEmptyDirectory(String),
+    PlanNameInvalid(String),
+    PlanNameUnsupported(String),
+    RepairReasonMissing,
+    RevisionAlreadyApplied(String),
+    RevisionBaselineInvalid(String, String),
+    RevisionDownChanged(String),
+    RevisionDownNotFound(String),
+    RevisionDownOrphaned(String),
+    RevisionFileExists(String),
+    RevisionIncludeCycle(String),
+    RevisionIncludeNotFound(String, String),
     RevisionNameInvalid(String),
+    RevisionNotChanged(i64),
+    RevisionNotPending(i64),
+    RevisionRecordNotFound(String),
     RevisionTimestampInvalid(num::ParseIntError, String),
     RevisionTimestampOutOfRange(String),
+    RevisionsFailedCheck(usize, String),
+    RevisionsFailedGitReview(usize, String),
+    RevisionsFailedLint(usize),
     RevisionsFailedReview(ReviewSummary),
+    RevisionsNeedRenumbering(usize),
+    RevisionsNotSigned(usize, String),
+    SignatureNotTrusted(String),
+    SignedManifestNotFound(String),
+    SigningKeyInvalid(String, String),
+    SnapshotNotConfigured,
+    SnapshotOutdated(String),
+    SnapshotRevisionsPending(usize),
+    TemplateNotFound(String),
     TomlInvalid(TomlError, String),
     TransactionCommandFound(String),
+    VariableNotDefined(String, String),
+    VerifyRevisionFailed(String, Box<Error>),
 }
 
 impl fmt::Display for Error {
@@ -36,24 +75,76 @@ impl fmt::Display for Error {
         use Error::*;
 
         match self {
+            ArchiveInvalid(pathstr, reason) => {
+                write!(
+                    f,
+                    "`{}` is not a valid revisions archive - {}",
+                    pathstr, reason
+                )
+            }
             BadEnvVar(err, var_name) => {
                 write!(f, "{} - {}", err, var_name)
             }
+            BaselineDumpFailed(err) => {
+                write!(f, "Failed to dump schema for baseline: {}", err)
+            }
+            BaselineStateMismatch(id) => {
+                write!(
+                    f,
+                    "The database must have all revisions through {id} applied and none after to create a baseline - \
+                    eg. use a new database with `jrny embark --through {id}`"
+                )
+            }
+            ChecksumAlgorithmUnknown(name) => {
+                write!(f, "Unknown checksum algorithm `{}`", name)
+            }
             ConfigNotFound(pathstr) => {
                 write!(f, "`{}` not found - run in directory with `jrny.toml` file or specify path to config with `-c /path/to/config`", pathstr)
             }
             DatabaseError(err) => {
                 write!(f, "{}", err)
             }
+            EditorFailed(editor) => {
+                write!(
+                    f,
+                    "Editor `{}` could not be run or exited with an error",
+                    editor
+                )
+            }
             EnvNotFound => {
                 write!(f, "`jrny-env.toml` must exist within same directory as config file or `--database-url` must be provided")
             }
+            EnvProtected => {
+                write!(
+                    f,
+                    "The environment is protected - pass `--confirm` to run this command"
+                )
+            }
+            FileNotFound(pathstr) => {
+                write!(f, "`{}` does not exist", pathstr)
+            }
             FileNotValid(pathstr) => {
                 write!(f, "`{}` is not a valid file", pathstr)
             }
+            GitFailed(err) => {
+                write!(f, "Failed to read revisions from git: {}", err)
+            }
+            GitRefNotFound(git_ref) => {
+                write!(f, "`{}` is not a commit in the git repository", git_ref)
+            }
             IoError(err) => {
                 write!(f, "{}", err)
             }
+            LintRuleUnknown(name) => {
+                write!(f, "Unknown lint rule `{}`", name)
+            }
+            LockfileNotFound(pathstr) => {
+                write!(
+                    f,
+                    "`{}` does not exist - create it with `jrny lock`",
+                    pathstr
+                )
+            }
             PathAlreadyExists(pathstr) => {
                 write!(f, "`{}` already exists", pathstr)
             }
@@ -66,13 +157,83 @@ impl fmt::Display for Error {
             PathNotEmptyDirectory(pathstr) => {
                 write!(f, "`{}` is not an empty directory", pathstr)
             }
+            PlanNameInvalid(name) => {
+                write!(
+                    f,
+                    "Invalid revision name `{}`: names cannot be empty or end with `.down`",
+                    name
+                )
+            }
+            PlanNameUnsupported(name) => {
+                write!(
+                    f,
+                    "Invalid revision name `{}`: names can only contain letters, digits, `.`, `_`, and `-` - \
+                    use eg. `add-users`, or set `slugify = true` under `[revisions.filenames]` to convert names automatically",
+                    name
+                )
+            }
+            RepairReasonMissing => write!(
+                f,
+                "A reason is required to repair a revision, given with --reason when not at a terminal"
+            ),
+            RevisionAlreadyApplied(filename) => {
+                write!(f, "Revision `{}` has already been applied", filename)
+            }
+            RevisionBaselineInvalid(entry, filename) => {
+                write!(
+                    f,
+                    "Invalid baseline entry `{}` in `{}`: expected `[filename] [checksum algorithm] [checksum]`",
+                    entry, filename
+                )
+            }
+            RevisionDownChanged(filename) => {
+                write!(
+                    f,
+                    "`{}` does not match the down revision present when the revision was applied",
+                    filename
+                )
+            }
+            RevisionDownNotFound(pathstr) => {
+                write!(
+                    f,
+                    "`{}` not found - cannot revert revision without a down file",
+                    pathstr
+                )
+            }
+            RevisionDownOrphaned(pathstr) => {
+                write!(
+                    f,
+                    "`{}` does not have a corresponding revision to revert",
+                    pathstr
+                )
+            }
+            RevisionFileExists(filename) => {
+                write!(
+                    f,
+                    "Revision `{}` still has a file - only revisions whose files no longer exist can be forgotten",
+                    filename
+                )
+            }
+            RevisionIncludeCycle(cycle) => {
+                write!(f, "Revision includes form a cycle: {}", cycle)
+            }
+            RevisionIncludeNotFound(pathstr, including) => {
+                write!(f, "`{}` included by `{}` not found", pathstr, including)
+            }
             RevisionNameInvalid(filename) => {
                 write!(
                     f,
-                    "Invalid revision name `{}`: expected `[id].[timestamp].[name].sql` eg. `001.1618370298.my-first-revision.sql`",
+                    "Invalid revision name `{}`: expected `[id].[timestamp].[name].sql` (or `.sql.gz` or `.sql.zst`) eg. `001.1618370298.my-first-revision.sql`",
                     filename
                 )
             }
+            RevisionNotChanged(id) => {
+                write!(f, "No applied revision with id {} has changed", id)
+            }
+            RevisionNotPending(id) => write!(f, "No pending revision with id {}", id),
+            RevisionRecordNotFound(target) => {
+                write!(f, "No applied revision matching `{}` found", target)
+            }
             RevisionTimestampInvalid(err, filename) => {
                 write!(f, "Invalid revision timestamp `{}`: {}", filename, err)
             }
@@ -83,6 +244,23 @@ impl fmt::Display for Error {
                     filename
                 )
             }
+            RevisionsFailedCheck(count, pathstr) => {
+                write!(
+                    f,
+                    "{} revision(s) do not match `{}` - if the changes are intended, update it with `jrny lock`",
+                    count, pathstr
+                )
+            }
+            RevisionsFailedGitReview(count, git_ref) => {
+                write!(
+                    f,
+                    "{} revision(s) conflict with those in `{}`",
+                    count, git_ref
+                )
+            }
+            RevisionsFailedLint(count) => {
+                write!(f, "{} revision(s) have lint errors", count)
+            }
             RevisionsFailedReview(summary) => {
                 let mut errs = String::new();
                 let sol = "\n  -";
@@ -111,6 +289,42 @@ impl fmt::Display for Error {
                     });
                 }
 
+                if summary.files_renamed() > 0 {
+                    errs.push_str(&match summary.files_renamed() {
+                        1 => format!("{sol} 1 revision file has been renamed after being applied"),
+                        count => format!(
+                            "{sol} {count} revision files have been renamed after being applied"
+                        ),
+                    });
+                    errs.push_str(" (accept with `jrny accept-renames`)");
+                }
+
+                if summary.baselines_incomplete() > 0 {
+                    errs.push_str(&match summary.baselines_incomplete() {
+                        1 => format!("{sol} 1 baseline has only some of its revisions applied"),
+                        count => format!(
+                            "{sol} {count} baselines have only some of their revisions applied"
+                        ),
+                    });
+                }
+
+                if summary.chains_broken() > 0 {
+                    errs.push_str(&match summary.chains_broken() {
+                        1 => format!("{sol} 1 revision record has been changed, removed, or inserted outside of jrny"),
+                        count => format!(
+                            "{sol} {count} revision records have been changed, removed, or inserted outside of jrny"
+                        ),
+                    });
+                    errs.push_str(" (accept with `jrny repair`)");
+                }
+
+                if summary.lint_failed() > 0 {
+                    errs.push_str(&match summary.lint_failed() {
+                        1 => format!("{sol} 1 pending revision has lint errors"),
+                        count => format!("{sol} {count} pending revisions have lint errors"),
+                    });
+                }
+
                 if summary.preceding_applied() > 0 {
                     errs.push_str(&match summary.preceding_applied() {
                         1 => format!("{sol} 1 pending revision has been inserted before revisions already applied"),
@@ -122,12 +336,80 @@ impl fmt::Display for Error {
 
                 write!(f, "The journey has problems:{}", errs)
             }
+            RevisionsNeedRenumbering(count) => {
+                write!(
+                    f,
+                    "{} revision(s) need to be renumbered - run `jrny renumber` to rename them",
+                    count
+                )
+            }
+            RevisionsNotSigned(count, pathstr) => {
+                write!(
+                    f,
+                    "{} revision(s) to run are not signed in `{}` - sign them with `jrny sign`",
+                    count, pathstr
+                )
+            }
+            SignatureNotTrusted(pathstr) => {
+                write!(
+                    f,
+                    "`{}` is not signed by a trusted key or has changed since being signed",
+                    pathstr
+                )
+            }
+            SignedManifestNotFound(pathstr) => {
+                write!(
+                    f,
+                    "`{}` does not exist - create it with `jrny sign`",
+                    pathstr
+                )
+            }
+            SigningKeyInvalid(key, reason) => {
+                write!(f, "`{}` is not a valid ed25519 key - {}", key, reason)
+            }
+            SnapshotNotConfigured => {
+                write!(
+                    f,
+                    "No snapshot configured - add a `[snapshot]` table with a `path` to the config file"
+                )
+            }
+            SnapshotOutdated(pathstr) => {
+                write!(
+                    f,
+                    "`{}` does not match the schema - run `jrny snapshot` to update it",
+                    pathstr
+                )
+            }
+            SnapshotRevisionsPending(count) => {
+                write!(
+                    f,
+                    "{} revision(s) are pending - apply them with `jrny embark` before taking a snapshot",
+                    count
+                )
+            }
+            TemplateNotFound(name) => {
+                write!(f, "No template named `{}` in `[revisions.templates]`", name)
+            }
             TomlInvalid(err, pathstr) => {
                 write!(f, "`{}` is invalid - {}", pathstr, err)
             }
             TransactionCommandFound(cmd) => {
                 write!(f, "Cannot use transaction commands: found `{}`", cmd)
             }
+            VariableNotDefined(name, filename) => {
+                write!(
+                    f,
+                    "`{}` uses variable `{}` - define it in the `[variables]` table of the config or environment file",
+                    filename, name
+                )
+            }
+            VerifyRevisionFailed(filename, err) => {
+                write!(
+                    f,
+                    "`{}` could not be applied to a new database - {}",
+                    filename, err
+                )
+            }
         }
     }
 }
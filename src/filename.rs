@@ -0,0 +1,235 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+
+use crate::{Error, Result};
+
+/// The format of the creation timestamp within revision filenames.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    /// Seconds since the Unix epoch, eg. `1618370298`
+    #[default]
+    Unix,
+    /// A UTC `YYYYMMDDHHMMSS` date and time, eg. `20210414031818`
+    Datetime,
+}
+
+const DATETIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// How the ids of new revisions are chosen.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdStrategy {
+    /// One more than the highest existing id, eg. `7` after `6`
+    #[default]
+    Sequential,
+    /// The UTC `YYYYMMDDHHMMSS` creation time as a number, eg. `20210414031818`,
+    /// so that revisions created on different branches are unlikely to collide
+    Timestamp,
+}
+
+impl IdStrategy {
+    /// The id for a new revision created at the given time, which is always
+    /// greater than the highest existing id (if any).
+    pub fn next_id(&self, last_id: Option<i64>, created_at: DateTime<Utc>) -> i64 {
+        let next_id = last_id.map_or(1, |id| id + 1);
+
+        match self {
+            Self::Sequential => next_id,
+            Self::Timestamp => created_at
+                .format(DATETIME_FORMAT)
+                .to_string()
+                .parse::<i64>()
+                .map_or(next_id, |id| id.max(next_id)),
+        }
+    }
+}
+
+/// The format of revision filenames, ie. `[id][separator][timestamp][separator][name].sql`.
+///
+/// Revisions named with the original `[id].[unix-timestamp].[name].sql` format
+/// are always recognized, regardless of the configured format.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilenameScheme {
+    /// The minimum number of digits in the id, which is padded with zeros
+    pub id_width: usize,
+    /// The format of the creation timestamp
+    pub timestamp: TimestampFormat,
+    /// The text between the id, timestamp, and name
+    pub separator: String,
+    /// Whether to convert the names of new revisions to lowercase words separated
+    /// by hyphens, eg. `add-users` for `Add Users!`
+    pub slugify: bool,
+}
+
+impl Default for FilenameScheme {
+    fn default() -> Self {
+        Self {
+            id_width: 3,
+            timestamp: TimestampFormat::Unix,
+            separator: ".".to_string(),
+            slugify: false,
+        }
+    }
+}
+
+impl FilenameScheme {
+    /// The filename for a new revision with the given id, creation time, and name.
+    pub fn filename(&self, id: i64, created_at: DateTime<Utc>, name: &str) -> String {
+        format!(
+            "{}{sep}{}{sep}{}.sql",
+            self.id(id),
+            self.timestamp(created_at),
+            name,
+            sep = self.separator,
+        )
+    }
+
+    /// The id as it appears in filenames, eg. `001`.
+    pub(crate) fn id(&self, id: i64) -> String {
+        format!("{:0width$}", id, width = self.id_width)
+    }
+
+    fn timestamp(&self, created_at: DateTime<Utc>) -> String {
+        match self.timestamp {
+            TimestampFormat::Unix => created_at.timestamp().to_string(),
+            TimestampFormat::Datetime => created_at.format(DATETIME_FORMAT).to_string(),
+        }
+    }
+
+    /// Parses the id, creation time, and name from the stem of a filename
+    /// in this format, if it is in this format.
+    pub(crate) fn parse(&self, stem: &str) -> Option<(i64, DateTime<Utc>, String)> {
+        let id_len = stem.find(|c: char| !c.is_ascii_digit())?;
+        let id = stem[..id_len].parse().ok()?;
+        let rest = stem[id_len..].strip_prefix(&self.separator)?;
+
+        let timestamp_len = match self.timestamp {
+            TimestampFormat::Unix => rest.find(|c: char| !c.is_ascii_digit())?,
+            TimestampFormat::Datetime => 14,
+        };
+        let timestamp = rest.get(..timestamp_len)?;
+        let name = rest[timestamp_len..].strip_prefix(&self.separator)?;
+
+        let created_at = match self.timestamp {
+            TimestampFormat::Unix => Utc.timestamp_opt(timestamp.parse().ok()?, 0).single()?,
+            TimestampFormat::Datetime => Utc.from_utc_datetime(
+                &NaiveDateTime::parse_from_str(timestamp, DATETIME_FORMAT).ok()?,
+            ),
+        };
+
+        Some((id, created_at, name.to_string()))
+    }
+
+    /// The name to use for a new revision given the requested name, which is slugified
+    /// if configured and must otherwise contain only ASCII letters, digits, `.`, `_`, and `-`.
+    pub fn revision_name(&self, name: &str) -> Result<String> {
+        let revision_name = if self.slugify {
+            slugify(name)
+        } else {
+            name.trim().to_string()
+        };
+
+        // Names ending with `.down` would be mistaken for down files
+        if revision_name.is_empty() || revision_name.ends_with(".down") {
+            return Err(Error::PlanNameInvalid(name.to_string()));
+        }
+
+        // Spaces, quotes, and the like make filenames awkward to use from a shell
+        if !self.slugify
+            && !revision_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            return Err(Error::PlanNameUnsupported(name.to_string()));
+        }
+
+        Ok(revision_name)
+    }
+}
+
+/// Converts the text to lowercase words (of letters and numbers) separated by hyphens.
+fn slugify(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn default_scheme_matches_original_format() {
+        let scheme = FilenameScheme::default();
+
+        assert_eq!(
+            scheme.filename(1, created_at(), "add-users"),
+            "001.1577836800.add-users.sql"
+        );
+        assert_eq!(
+            scheme.parse("001.1577836800.add.users"),
+            Some((1, created_at(), "add.users".to_string()))
+        );
+    }
+
+    #[test]
+    fn custom_scheme_round_trips() {
+        let scheme = FilenameScheme {
+            id_width: 5,
+            timestamp: TimestampFormat::Datetime,
+            separator: "_".to_string(),
+            slugify: true,
+        };
+        let filename = scheme.filename(42, created_at(), "add_users");
+
+        assert_eq!(filename, "00042_20200101000000_add_users.sql");
+        assert_eq!(
+            scheme.parse(filename.strip_suffix(".sql").unwrap()),
+            Some((42, created_at(), "add_users".to_string()))
+        );
+        assert_eq!(scheme.parse("001.1577836800.add-users"), None);
+    }
+
+    #[test]
+    fn timestamp_ids_follow_existing_ids() {
+        let strategy = IdStrategy::Timestamp;
+
+        assert_eq!(strategy.next_id(None, created_at()), 20200101000000);
+        assert_eq!(strategy.next_id(Some(42), created_at()), 20200101000000);
+        // Eg. when another revision was created within the same second
+        assert_eq!(
+            strategy.next_id(Some(20200101000000), created_at()),
+            20200101000001
+        );
+        assert_eq!(IdStrategy::Sequential.next_id(Some(42), created_at()), 43);
+    }
+
+    #[test]
+    fn revision_name_is_slugified_or_validated() {
+        let mut scheme = FilenameScheme::default();
+
+        assert_eq!(
+            scheme.revision_name(" add_users.v2 ").unwrap(),
+            "add_users.v2"
+        );
+        assert!(matches!(
+            scheme.revision_name("Add Users!"),
+            Err(Error::PlanNameUnsupported(_))
+        ));
+        assert!(scheme.revision_name("../escape").is_err());
+        assert!(scheme.revision_name("undo.down").is_err());
+
+        scheme.slugify = true;
+
+        assert_eq!(scheme.revision_name("Add Users!").unwrap(), "add-users");
+        assert!(scheme.revision_name("!!!").is_err());
+    }
+}
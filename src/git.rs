@@ -0,0 +1,162 @@
+//! Reads files as of a git commit straight from the local repository, without
+//! checking anything out, by running `git` itself.
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::{Error, Result};
+
+/// The revisions directory as of a commit, eg. a branch or tag.
+#[derive(Debug)]
+pub(crate) struct GitTree {
+    /// The revisions directory in the working tree, within which `git` is run
+    dir: PathBuf,
+    /// The commit, verified to exist
+    rev: String,
+}
+
+impl GitTree {
+    /// Opens the directory as of the commit named by the ref, failing if it is not
+    /// within a repository that has the commit.
+    pub(crate) fn open(dir: &Path, git_ref: &str) -> Result<Self> {
+        let tree = Self {
+            dir: dir.to_owned(),
+            rev: git_ref.to_string(),
+        };
+
+        let output = tree.git(
+            &["rev-parse", "--verify", "--quiet"],
+            &format!("{}^{{commit}}", git_ref),
+        )?;
+
+        if !output.status.success() {
+            return Err(Error::GitRefNotFound(git_ref.to_string()));
+        }
+
+        Ok(Self {
+            rev: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ..tree
+        })
+    }
+
+    /// The paths of the files directly within the revisions directory.
+    pub(crate) fn files(&self) -> Result<Vec<PathBuf>> {
+        let output = self.git(&["ls-tree", "-z", &self.rev], "./")?;
+
+        if !output.status.success() {
+            return Err(failed("ls-tree", &output));
+        }
+
+        // Entries are formatted as `<mode> <type> <object>\t<name>`
+        Ok(output
+            .stdout
+            .split(|byte| *byte == 0)
+            .filter_map(|entry| std::str::from_utf8(entry).ok())
+            .filter_map(|entry| entry.split_once('\t'))
+            .filter(|(meta, _)| meta.split(' ').nth(1) == Some("blob"))
+            .map(|(_, name)| self.dir.join(name))
+            .collect())
+    }
+
+    /// The contents of the file at the path, which must be within the working tree
+    /// of the repository, if the commit has it.
+    pub(crate) fn read(&self, p: &Path) -> Result<Option<Vec<u8>>> {
+        let output = self.git(&["cat-file", "blob"], &self.object(p))?;
+
+        Ok(output.status.success().then_some(output.stdout))
+    }
+
+    /// Whether the commit has a file at the path, which must be within the working tree
+    /// of the repository, without reading its contents.
+    pub(crate) fn has_file(&self, p: &Path) -> Result<bool> {
+        // The type rather than just whether the object exists, so that directories
+        // are not taken for files
+        let output = self.git(&["cat-file", "-t"], &self.object(p))?;
+
+        Ok(output.status.success() && output.stdout.trim_ascii() == b"blob")
+    }
+
+    /// Names the file as of the commit, relative to the revisions directory, eg.
+    /// `<commit>:./shared/fn.sql`.
+    fn object(&self, p: &Path) -> String {
+        // Paths are always built by joining onto the directory, so any `..` is kept
+        // for `git` to resolve
+        let relative: Vec<String> = p
+            .strip_prefix(&self.dir)
+            .unwrap_or(p)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        format!("{}:./{}", self.rev, relative.join("/"))
+    }
+
+    fn git(&self, args: &[&str], target: &str) -> Result<Output> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .arg(target)
+            .output()
+            .map_err(|e| Error::GitFailed(format!("could not run `git` - {}", e)))
+    }
+}
+
+fn failed(command: &str, output: &Output) -> Error {
+    Error::GitFailed(format!(
+        "`git {}` failed - {}",
+        command,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+/// Resolves `.` and `..` components of the path without touching the file system,
+/// since the file may not exist outside of the commit.
+pub(crate) fn normalize(p: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in p.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_names_paths_relative_to_the_directory() {
+        let tree = GitTree {
+            dir: PathBuf::from("db/./revisions"),
+            rev: "abc123".to_string(),
+        };
+
+        assert_eq!(
+            tree.object(Path::new("db/revisions/001.1577836800.a.sql")),
+            "abc123:./001.1577836800.a.sql"
+        );
+        assert_eq!(
+            tree.object(&Path::new("db/revisions").join("../shared/fn.sql")),
+            "abc123:./../shared/fn.sql"
+        );
+    }
+
+    #[test]
+    fn normalize_resolves_relative_components() {
+        assert_eq!(
+            normalize(Path::new("db/./revisions/../shared/fn.sql")),
+            PathBuf::from("db/shared/fn.sql")
+        );
+        assert_eq!(
+            normalize(Path::new("../fn.sql")),
+            PathBuf::from("../fn.sql")
+        );
+    }
+}
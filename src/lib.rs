@@ -1,10 +1,22 @@
+mod archive;
+mod checksum;
 mod commands;
 pub mod context;
 mod db;
 mod error;
+mod filename;
+mod git;
+mod lint;
+mod lockfile;
 mod revisions;
+mod signature;
+mod sql;
 
-pub use commands::{begin, embark, plan, review};
+pub use commands::{
+    accept_renames, baseline, begin, check, diff, embark, forget, lint, lock, mark_applied,
+    package, plan, renumber, repair, revert, review, review_against, sign, snapshot, upgrade,
+    verify, RevertTarget,
+};
 pub use error::Error;
 
 pub(crate) use db::executor::Executor;
@@ -15,6 +27,12 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// (For CLI usage) The default name of the config file
 pub const CONF: &str = "jrny.toml";
 
+/// The name of the lockfile of revision checksums, kept next to the config file
+pub const LOCK: &str = "jrny.lock";
+
+/// The name of the signed manifest of revision checksums, kept next to the config file
+pub const SIGNED: &str = "jrny.signed";
+
 /// (For CLI usage) The default name of the environment file
 pub const ENV: &str = "jrny-env.toml";
 
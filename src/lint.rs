@@ -0,0 +1,588 @@
+//! A static linter for revision SQL, flagging patterns that are risky to run against
+//! production databases, eg. statements that hold long locks on existing tables.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::sql::{self, Token, TokenKind};
+use crate::Error;
+
+/// Directive skipping the listed rules for the revision containing it,
+/// eg. `-- jrny:lint-ignore drop-column, missing-where`.
+const IGNORE_DIRECTIVE: &str = "-- jrny:lint-ignore ";
+
+/// The patterns flagged by the linter.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LintRule {
+    /// `CREATE INDEX` on an existing table without `CONCURRENTLY`
+    IndexWithoutConcurrently,
+    /// `ADD COLUMN ... NOT NULL` on an existing table without a default
+    NotNullWithoutDefault,
+    /// `ALTER COLUMN ... TYPE` on an existing table
+    AlterColumnType,
+    /// `DROP TABLE`
+    DropTable,
+    /// `ALTER TABLE ... DROP COLUMN`
+    DropColumn,
+    /// `UPDATE` or `DELETE` without `WHERE`
+    MissingWhere,
+    /// Locking an existing table without having set `lock_timeout`
+    MissingLockTimeout,
+    /// `CONCURRENTLY` within a `begin;` transaction block
+    ConcurrentlyInTransaction,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 8] = [
+        LintRule::IndexWithoutConcurrently,
+        LintRule::NotNullWithoutDefault,
+        LintRule::AlterColumnType,
+        LintRule::DropTable,
+        LintRule::DropColumn,
+        LintRule::MissingWhere,
+        LintRule::MissingLockTimeout,
+        LintRule::ConcurrentlyInTransaction,
+    ];
+
+    /// The name of the rule, as used in the config and ignore directives.
+    pub fn name(&self) -> &'static str {
+        use LintRule::*;
+
+        match self {
+            IndexWithoutConcurrently => "index-without-concurrently",
+            NotNullWithoutDefault => "not-null-without-default",
+            AlterColumnType => "alter-column-type",
+            DropTable => "drop-table",
+            DropColumn => "drop-column",
+            MissingWhere => "missing-where",
+            MissingLockTimeout => "missing-lock-timeout",
+            ConcurrentlyInTransaction => "concurrently-in-transaction",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        use LintRule::*;
+
+        match self {
+            IndexWithoutConcurrently => {
+                "Creating an index without `CONCURRENTLY` blocks writes to the table while it builds"
+            }
+            NotNullWithoutDefault => {
+                "Adding a `NOT NULL` column without a default fails if the table has any rows"
+            }
+            AlterColumnType => {
+                "Changing the type of a column can rewrite the table while blocking reads and writes"
+            }
+            DropTable => "Dropping a table loses its data and breaks anything still using it",
+            DropColumn => "Dropping a column loses its data and breaks anything still using it",
+            MissingWhere => "`UPDATE` or `DELETE` without `WHERE` affects every row",
+            MissingLockTimeout => {
+                "Locking a table without first setting `lock_timeout` can queue all other queries behind it"
+            }
+            ConcurrentlyInTransaction => "`CONCURRENTLY` cannot be used within a transaction block",
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|rule| rule.name() == name)
+            .ok_or_else(|| Error::LintRuleUnknown(name.to_string()))
+    }
+}
+
+/// How findings of a rule are treated, where errors fail review of pending revisions.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintLevel::Off => write!(f, "off"),
+            LintLevel::Warning => write!(f, "warning"),
+            LintLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The level of each rule by name, with rules that are not listed being warnings.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "HashMap<String, LintLevel>")]
+pub struct LintSettings {
+    levels: HashMap<LintRule, LintLevel>,
+}
+
+impl LintSettings {
+    pub fn level(&self, rule: LintRule) -> LintLevel {
+        self.levels
+            .get(&rule)
+            .copied()
+            .unwrap_or(LintLevel::Warning)
+    }
+}
+
+impl TryFrom<HashMap<String, LintLevel>> for LintSettings {
+    type Error = Error;
+
+    fn try_from(levels: HashMap<String, LintLevel>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            levels: levels
+                .into_iter()
+                .map(|(name, level)| Ok((name.parse()?, level)))
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+}
+
+/// A risky pattern found in a revision.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub level: LintLevel,
+    /// The line of the statement in the revision contents (with includes expanded)
+    pub line: usize,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] line {}: {}",
+            self.rule,
+            self.line,
+            self.rule.description()
+        )
+    }
+}
+
+/// A statement of the revision, without its comments and whitespace.
+struct Statement<'a> {
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Statement<'a> {
+    /// Whether the token at the index is the given keyword.
+    fn is(&self, index: usize, keyword: &str) -> bool {
+        is_keyword(self.tokens.get(index), keyword)
+    }
+
+    /// The index after the given keywords, if they are found at the index.
+    fn skip(&self, index: usize, keywords: &[&str]) -> usize {
+        match keywords
+            .iter()
+            .enumerate()
+            .all(|(i, keyword)| self.is(index + i, keyword))
+        {
+            true => index + keywords.len(),
+            false => index,
+        }
+    }
+
+    /// Whether the keyword appears outside of any parentheses.
+    fn has_top_level(&self, keyword: &str) -> bool {
+        let mut depth = 0;
+
+        self.tokens.iter().any(|token| {
+            match token.text {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                _ => {}
+            }
+
+            depth == 0 && is_keyword(Some(token), keyword)
+        })
+    }
+
+    fn mentions(&self, keyword: &str) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| is_keyword(Some(token), keyword))
+    }
+
+    /// The unqualified name of the table starting at the index, along with the index after it.
+    fn name_at(&self, index: usize) -> Option<(String, usize)> {
+        let mut name = None;
+        let mut index = index;
+
+        while let Some(part) = self.tokens.get(index).and_then(identifier) {
+            name = Some(part);
+            index += 1;
+
+            if self.tokens.get(index).map(|t| t.text) != Some(".") {
+                break;
+            }
+            index += 1;
+        }
+
+        name.map(|name| (name, index))
+    }
+
+    /// The comma-separated actions of an `ALTER TABLE` statement starting at the index.
+    fn actions(&self, index: usize) -> Vec<Statement<'a>> {
+        let mut actions = vec![Statement { tokens: vec![] }];
+        let mut depth = 0;
+
+        for token in self.tokens.iter().skip(index) {
+            match token.text {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                "," if depth == 0 => {
+                    actions.push(Statement { tokens: vec![] });
+                    continue;
+                }
+                _ => {}
+            }
+
+            actions.last_mut().unwrap().tokens.push(*token);
+        }
+
+        actions
+    }
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    token.is_some_and(|t| t.kind == TokenKind::Word && t.text.eq_ignore_ascii_case(keyword))
+}
+
+/// The name of an identifier token, which is case-insensitive unless quoted.
+fn identifier(token: &Token) -> Option<String> {
+    match token.kind {
+        TokenKind::Word => Some(token.text.to_lowercase()),
+        TokenKind::QuotedIdentifier => Some(token.text.trim_matches('"').replace("\"\"", "\"")),
+        _ => None,
+    }
+}
+
+/// Splits the contents into statements along with their offsets, keeping the
+/// `BEGIN ATOMIC ... END` body of a function within its statement.
+fn statements(contents: &str) -> Vec<(usize, Statement<'_>)> {
+    let mut statements = Vec::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut atomic_depth = 0;
+    // Whether the previous token ended a statement within the body, so that an
+    // `END` starting the next one closes the body rather than eg. a `CASE`
+    let mut body_statement_ended = false;
+
+    for token in sql::tokenize(contents) {
+        if is_keyword(Some(&token), "atomic") && is_keyword(tokens.last(), "begin") {
+            atomic_depth += 1;
+        } else if is_keyword(Some(&token), "end") && atomic_depth > 0 && body_statement_ended {
+            atomic_depth -= 1;
+        }
+
+        if !matches!(token.kind, TokenKind::Comment | TokenKind::Whitespace) {
+            body_statement_ended = token.text == ";" || is_keyword(Some(&token), "atomic");
+        }
+
+        match token.kind {
+            TokenKind::Comment | TokenKind::Whitespace => {}
+            TokenKind::Symbol if token.text == ";" && atomic_depth == 0 => {
+                if !tokens.is_empty() {
+                    let tokens: Vec<Token> = std::mem::take(&mut tokens);
+                    statements.push((tokens[0].offset, Statement { tokens }));
+                }
+            }
+            _ => tokens.push(token),
+        }
+    }
+
+    if !tokens.is_empty() {
+        statements.push((tokens[0].offset, Statement { tokens }));
+    }
+
+    statements
+}
+
+/// The rules skipped by ignore directives in the contents.
+fn ignored_rules(contents: &str) -> HashSet<LintRule> {
+    contents
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix(IGNORE_DIRECTIVE))
+        .flat_map(|names| names.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter_map(|name| name.parse().ok())
+        .collect()
+}
+
+/// Finds the risky patterns in the revision contents, leaving out rules that are
+/// turned off or ignored by a directive within the contents.
+pub fn lint(contents: &str, settings: &LintSettings) -> Vec<LintFinding> {
+    use LintRule::*;
+
+    let ignored = ignored_rules(contents);
+    let mut found = Vec::new();
+
+    // Tables created by the revision itself are not yet in use
+    let mut created = HashSet::new();
+    let mut in_transaction = false;
+    let mut lock_timeout_set = false;
+    let mut lock_timeout_flagged = false;
+
+    for (offset, stmt) in statements(contents) {
+        let line = contents[..offset].matches('\n').count() + 1;
+        let mut flag = |rule| found.push((rule, line));
+        let mut locks_table = false;
+
+        if stmt.is(0, "begin") || (stmt.is(0, "start") && stmt.is(1, "transaction")) {
+            in_transaction = true;
+            continue;
+        }
+        if ["commit", "end", "rollback", "abort"]
+            .iter()
+            .any(|cmd| stmt.is(0, cmd))
+        {
+            in_transaction = false;
+            continue;
+        }
+
+        // Refreshing a materialized view concurrently is allowed within transactions
+        if in_transaction && stmt.mentions("concurrently") && !stmt.is(0, "refresh") {
+            flag(ConcurrentlyInTransaction);
+        }
+
+        if stmt.mentions("lock_timeout")
+            || stmt
+                .tokens
+                .iter()
+                .any(|t| t.kind == TokenKind::String && t.text.contains("lock_timeout"))
+        {
+            lock_timeout_set = true;
+        }
+
+        if stmt.is(0, "create") {
+            let mut i = 1;
+
+            while ["global", "local", "temp", "temporary", "unlogged"]
+                .iter()
+                .any(|kw| stmt.is(i, kw))
+            {
+                i += 1;
+            }
+
+            if stmt.is(i, "table") {
+                let i = stmt.skip(i + 1, &["if", "not", "exists"]);
+
+                if let Some((table, _)) = stmt.name_at(i) {
+                    created.insert(table);
+                }
+            }
+
+            let i = stmt.skip(1, &["unique"]);
+
+            if stmt.is(i, "index") {
+                let concurrently = stmt.is(i + 1, "concurrently");
+                let table = stmt
+                    .tokens
+                    .iter()
+                    .position(|t| is_keyword(Some(t), "on"))
+                    .and_then(|on| stmt.name_at(stmt.skip(on + 1, &["only"])));
+
+                if let Some((table, _)) = table {
+                    if !concurrently && !created.contains(&table) {
+                        flag(IndexWithoutConcurrently);
+                        locks_table = true;
+                    }
+                }
+            }
+        }
+
+        if stmt.is(0, "alter") && stmt.is(1, "table") {
+            let i = stmt.skip(2, &["if", "exists"]);
+            let i = stmt.skip(i, &["only"]);
+
+            if let Some((table, after)) = stmt.name_at(i) {
+                if !created.contains(&table) {
+                    locks_table = true;
+
+                    for action in stmt.actions(after) {
+                        if let Some(rule) = alter_table_rule(&action) {
+                            flag(rule);
+                        }
+                    }
+                }
+            }
+        }
+
+        if stmt.is(0, "drop") && stmt.is(1, "table") {
+            flag(DropTable);
+            locks_table = true;
+        }
+
+        if (stmt.is(0, "update") || stmt.is(0, "delete")) && !stmt.has_top_level("where") {
+            flag(MissingWhere);
+        }
+
+        // Only flagged once, since a single setting covers the whole revision
+        if locks_table && !lock_timeout_set && !lock_timeout_flagged {
+            flag(MissingLockTimeout);
+            lock_timeout_flagged = true;
+        }
+    }
+
+    found
+        .into_iter()
+        .filter(|(rule, _)| !ignored.contains(rule))
+        .map(|(rule, line)| LintFinding {
+            rule,
+            level: settings.level(rule),
+            line,
+        })
+        .filter(|finding| finding.level != LintLevel::Off)
+        .collect()
+}
+
+/// The rule flagged by an action of an `ALTER TABLE` statement on an existing table, if any.
+fn alter_table_rule(action: &Statement) -> Option<LintRule> {
+    if action.is(0, "add") {
+        let i = action.skip(1, &["column"]);
+        let i = action.skip(i, &["if", "not", "exists"]);
+        let adds_constraint = [
+            "constraint",
+            "primary",
+            "unique",
+            "foreign",
+            "check",
+            "exclude",
+        ]
+        .iter()
+        .any(|kw| action.is(i, kw));
+
+        let not_null =
+            (0..action.tokens.len()).any(|j| action.is(j, "not") && action.is(j + 1, "null"));
+        let has_default = action.mentions("default") || action.mentions("generated");
+
+        if !adds_constraint && not_null && !has_default {
+            return Some(LintRule::NotNullWithoutDefault);
+        }
+    }
+
+    if action.is(0, "drop") && !action.is(1, "constraint") {
+        return Some(LintRule::DropColumn);
+    }
+
+    if action.is(0, "alter") {
+        let i = action.skip(1, &["column"]);
+
+        if action.is(i + 1, "type") || action.skip(i + 1, &["set", "data", "type"]) > i + 1 {
+            return Some(LintRule::AlterColumnType);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(contents: &str) -> Vec<(LintRule, usize)> {
+        lint(contents, &LintSettings::default())
+            .into_iter()
+            .map(|finding| (finding.rule, finding.line))
+            .collect()
+    }
+
+    #[test]
+    fn lint_flags_risky_statements_on_existing_tables() {
+        use LintRule::*;
+
+        assert_eq!(
+            rules(
+                "begin;
+create index users_name on users (name);
+alter table users
+    add column email text not null,
+    alter column age type bigint,
+    drop column nickname;
+update users set name = 'x';
+delete from posts where id in (select id from old_posts);
+drop table posts;
+commit;
+"
+            ),
+            vec![
+                (IndexWithoutConcurrently, 2),
+                (MissingLockTimeout, 2),
+                (NotNullWithoutDefault, 3),
+                (AlterColumnType, 3),
+                (DropColumn, 3),
+                (MissingWhere, 7),
+                (DropTable, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_allows_changes_to_tables_created_in_the_revision() {
+        assert_eq!(
+            rules(
+                "begin;
+set local lock_timeout = '5s';
+create table app.users (id int);
+create index users_id on app.users (id);
+alter table users add column name text not null;
+commit;
+"
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn lint_flags_concurrently_in_transaction() {
+        assert_eq!(
+            rules("begin;\ncreate index concurrently users_name on users (name);\ncommit;\n"),
+            vec![(LintRule::ConcurrentlyInTransaction, 2)]
+        );
+        assert_eq!(
+            rules("create index concurrently users_name on users (name);\n"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn lint_skips_function_bodies() {
+        assert_eq!(
+            rules(
+                "create function reset() returns void begin atomic\n    delete from t;\nend;\n\
+                 create function purge() returns void as $$ delete from t; $$ language sql;\n"
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn lint_respects_levels_and_ignore_directives() {
+        let settings: LintSettings =
+            toml::from_str("drop-table = \"error\"\nmissing-lock-timeout = \"off\"").unwrap();
+
+        assert_eq!(
+            lint(
+                "-- jrny:lint-ignore missing-where\ndelete from t;\ndrop table t;",
+                &settings
+            ),
+            vec![LintFinding {
+                rule: LintRule::DropTable,
+                level: LintLevel::Error,
+                line: 3,
+            }]
+        );
+        assert!(toml::from_str::<LintSettings>("drop-tables = \"off\"").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! The lockfile recording the checksum of every revision, so that revisions changed
+//! after being committed can be found without connecting to a database.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::ChecksumAlgorithm;
+use crate::revisions::{filename_stem, RevisionFile};
+use crate::{Error, Result};
+
+const HEADER: &str = "\
+# Checksums of the revisions, maintained by `jrny plan`, `jrny renumber`, and `jrny lock` - do not edit by hand.
+#
+# This file MUST BE INCLUDED in version control.
+";
+
+/// A revision as recorded in the lockfile.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LockedRevision {
+    pub id: i64,
+    /// The full name of the file, including id, timestamp, and extension
+    pub filename: String,
+    /// The name of the algorithm used to compute the checksum, eg. `sha256+eol`
+    pub checksum_algorithm: String,
+    pub checksum: String,
+    /// The checksum of the down file, if the revision has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_checksum: Option<String>,
+}
+
+impl LockedRevision {
+    pub(crate) fn algorithm(&self) -> Result<ChecksumAlgorithm> {
+        self.checksum_algorithm.parse()
+    }
+}
+
+impl From<&RevisionFile> for LockedRevision {
+    fn from(file: &RevisionFile) -> Self {
+        Self {
+            id: file.id,
+            filename: file.filename.clone(),
+            checksum_algorithm: file.checksum_algorithm.to_string(),
+            checksum: file.checksum.clone(),
+            down_checksum: file.down.as_ref().map(|down| down.checksum.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "revision")]
+    pub revisions: Vec<LockedRevision>,
+}
+
+impl Lockfile {
+    /// Reads the lockfile at the path, if it exists.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let lockfile: Self = toml::from_str(&contents)
+            .map_err(|e| Error::TomlInvalid(e, path.display().to_string()))?;
+
+        for revision in &lockfile.revisions {
+            revision.algorithm()?;
+        }
+
+        Ok(Some(lockfile))
+    }
+
+    /// A lockfile recording exactly the given revisions.
+    pub fn from_files(files: &[RevisionFile]) -> Self {
+        let mut lockfile = Self::default();
+
+        for file in files {
+            lockfile.insert(file);
+        }
+
+        lockfile
+    }
+
+    /// Records the revision, replacing any existing record of the same file.
+    pub fn insert(&mut self, file: &RevisionFile) {
+        self.remove(&file.filename);
+        self.revisions.push(LockedRevision::from(file));
+        self.revisions
+            .sort_by(|a, b| (a.id, &a.filename).cmp(&(b.id, &b.filename)));
+    }
+
+    /// Removes the record of the file with the given name, ignoring the extension,
+    /// returning whether there was one.
+    pub fn remove(&mut self, filename: &str) -> bool {
+        let stem = filename_stem(filename);
+        let count = self.revisions.len();
+
+        self.revisions
+            .retain(|revision| filename_stem(&revision.filename) != stem);
+
+        self.revisions.len() != count
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let body = toml::to_string(self).expect("lockfile should serialize");
+
+        fs::write(path, format!("{}\n{}", HEADER, body))?;
+
+        Ok(())
+    }
+
+    /// Compares the revision files against the lockfile. Files are matched by name
+    /// ignoring the extension, as in review, and those recorded in a baseline revision
+    /// are not considered removed.
+    pub fn check(&self, files: &[RevisionFile]) -> Result<LockCheck> {
+        let mut check = LockCheck::default();
+
+        let locked: HashMap<&str, &LockedRevision> = self
+            .revisions
+            .iter()
+            .map(|revision| (filename_stem(&revision.filename), revision))
+            .collect();
+        let last_locked = self.revisions.iter().map(|revision| revision.id).max();
+
+        let mut id_counts: HashMap<i64, usize> = HashMap::new();
+        for file in files {
+            *id_counts.entry(file.id).or_default() += 1;
+        }
+
+        for file in files {
+            match locked.get(filename_stem(&file.filename)) {
+                Some(revision) => {
+                    if file.checksum_with(revision.algorithm()?) != revision.checksum {
+                        check.problem(file, LockProblem::FileChanged);
+                    }
+                }
+                // Baselines take the id of the last revision they stand in for
+                None if file.baselined.is_empty()
+                    && last_locked.is_some_and(|last| file.id < last) =>
+                {
+                    check.problem(file, LockProblem::PrecedesLocked);
+                }
+                None => check.unlocked.push(file.filename.clone()),
+            }
+
+            if id_counts[&file.id] > 1 {
+                check.problem(file, LockProblem::DuplicateId);
+            }
+        }
+
+        let present: HashSet<&str> = files
+            .iter()
+            .map(|file| filename_stem(&file.filename))
+            .chain(
+                files
+                    .iter()
+                    .flat_map(|file| &file.baselined)
+                    .map(|baselined| filename_stem(&baselined.filename)),
+            )
+            .collect();
+
+        for revision in &self.revisions {
+            if !present.contains(filename_stem(&revision.filename)) {
+                check
+                    .problems
+                    .push((revision.filename.clone(), LockProblem::FileNotFound));
+            }
+        }
+
+        check.problems.sort();
+
+        Ok(check)
+    }
+}
+
+/// The ways in which the revision files can disagree with the lockfile.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LockProblem {
+    DuplicateId,
+    FileChanged,
+    FileNotFound,
+    PrecedesLocked,
+}
+
+impl fmt::Display for LockProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LockProblem::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                DuplicateId => "Revision has a duplicate id",
+                FileChanged => "File has changed since being locked",
+                FileNotFound => "File could not be found",
+                PrecedesLocked => "Revision is not locked but comes before revisions that are",
+            }
+        )
+    }
+}
+
+/// The result of comparing the revision files against the lockfile.
+#[derive(Debug, Default)]
+pub struct LockCheck {
+    /// The filename of each revision with a problem, sorted by filename
+    pub problems: Vec<(String, LockProblem)>,
+    /// Revisions after all those locked that are not yet locked themselves
+    pub unlocked: Vec<String>,
+}
+
+impl LockCheck {
+    fn problem(&mut self, file: &RevisionFile, problem: LockProblem) {
+        self.problems.push((file.filename.clone(), problem));
+    }
+
+    /// The number of revisions with problems.
+    pub fn failed(&self) -> usize {
+        self.problems
+            .iter()
+            .map(|(filename, _)| filename)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revisions::revision_file;
+
+    #[test]
+    fn lockfile_round_trips_through_toml() {
+        let lockfile = Lockfile::from_files(&[
+            revision_file(2, "b", "select 2;"),
+            revision_file(1, "a", "select 1;"),
+        ]);
+        let contents = toml::to_string(&lockfile).unwrap();
+
+        assert_eq!(lockfile.revisions[0].filename, "001.1577836800.a.sql");
+        assert_eq!(toml::from_str::<Lockfile>(&contents).unwrap(), lockfile);
+    }
+
+    #[test]
+    fn check_reports_changed_removed_duplicate_and_inserted_revisions() {
+        use LockProblem::*;
+
+        let lockfile = Lockfile::from_files(&[
+            revision_file(1, "a", "select 1;"),
+            revision_file(2, "b", "select 2;"),
+            revision_file(4, "d", "select 4;"),
+        ]);
+
+        let check = lockfile
+            .check(&[
+                revision_file(1, "a", "select 1;"),
+                revision_file(2, "b", "select 'two';"),
+                revision_file(3, "c", "select 3;"),
+                revision_file(4, "d", "select 4;"),
+                revision_file(4, "e", "select 5;"),
+                revision_file(6, "f", "select 6;"),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            check.problems,
+            vec![
+                ("002.1577836800.b.sql".to_string(), FileChanged),
+                ("003.1577836800.c.sql".to_string(), PrecedesLocked),
+                ("004.1577836800.d.sql".to_string(), DuplicateId),
+                ("004.1577836800.e.sql".to_string(), DuplicateId),
+            ]
+        );
+        assert_eq!(
+            check.unlocked,
+            vec!["004.1577836800.e.sql", "006.1577836800.f.sql"]
+        );
+
+        let check = lockfile
+            .check(&[revision_file(1, "a", "select 1;")])
+            .unwrap();
+
+        assert_eq!(
+            check.problems,
+            vec![
+                ("002.1577836800.b.sql".to_string(), FileNotFound),
+                ("004.1577836800.d.sql".to_string(), FileNotFound),
+            ]
+        );
+    }
+}
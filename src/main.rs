@@ -1,13 +1,14 @@
-use std::io::Write;
+use std::fs;
+use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use std::process::ExitCode;
 
 use clap::Parser;
-use log::{warn, Level, LevelFilter, Log, Metadata, Record};
+use log::{info, warn, Level, LevelFilter, Log, Metadata, Record};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-use jrny::context::{Config, Environment};
-use jrny::{Error as JrnyError, Result as JrnyResult, CONF, ENV};
+use jrny::context::{Config, Environment, RevisionSource};
+use jrny::{Error as JrnyError, Result as JrnyResult, RevertTarget, CONF, ENV};
 
 
 #[derive(Parser, Debug)]
@@ -44,7 +45,22 @@ enum SubCommand {
     Begin(Begin),
     Plan(Plan),
     Review(Review),
+    Lint(Lint),
+    Check(Check),
+    Lock(Lock),
+    Sign(Sign),
     Embark(Embark),
+    Revert(Revert),
+    Baseline(Baseline),
+    Renumber(Renumber),
+    AcceptRenames(AcceptRenames),
+    Repair(Repair),
+    MarkApplied(MarkApplied),
+    Forget(Forget),
+    Snapshot(Snapshot),
+    Verify(Verify),
+    Package(Package),
+    Upgrade(Upgrade),
 }
 
 #[derive(Parser, Debug)]
@@ -81,6 +97,60 @@ struct Plan {
 Title of the new revision. Surround with quotation marks to include whitespace in the title."
     )]
     name: String,
+
+    #[arg(
+        help = "The template for the new revision, from those in `[revisions.templates]`",
+        long,
+        short,
+        conflicts_with_all = ["from_file", "stdin", "diff"],
+    )]
+    template: Option<String>,
+
+    #[arg(
+        help = "Use the contents of an existing file for the new revision",
+        long,
+        conflicts_with_all = ["stdin", "diff"],
+    )]
+    from_file: Option<PathBuf>,
+
+    #[arg(
+        help = "Read the contents of the new revision from stdin",
+        long,
+        conflicts_with_all = ["edit", "diff"],
+    )]
+    stdin: bool,
+
+    #[arg(
+        help = "Generate the new revision by comparing the schemas of two databases",
+        long_help = "\
+Generate the new revision by comparing the schemas of two databases through the Postgres \
+catalogs, eg. one built by replaying revisions (`--from`) and one built from a schema file \
+(`--to`). Tables, columns, indexes, constraints, views, functions, and grants are compared, \
+and no revision is created if there are no differences.",
+        long,
+        requires_all = ["from", "to"],
+    )]
+    diff: bool,
+
+    #[arg(
+        help = "Connection string of the database with the current schema, for `--diff`",
+        long,
+        requires = "diff",
+    )]
+    from: Option<String>,
+
+    #[arg(
+        help = "Connection string of the database with the desired schema, for `--diff`",
+        long,
+        requires = "diff",
+    )]
+    to: Option<String>,
+
+    #[arg(
+        help = "Open the new revision in $VISUAL or $EDITOR, removing it if left empty",
+        long,
+    )]
+    edit: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -89,7 +159,12 @@ Title of the new revision. Surround with quotation marks to include whitespace i
     long_about = "\
 Lists all revisions along with dates created and applied, along with \
 any errors found such as a revision having been changed or removed \
-after being applied."
+after being applied. Pending revisions are also linted for risky SQL.
+
+With `--git-base`, revisions are instead compared with those of a git commit, eg. the \
+base branch of a pull request, without connecting to a database. This fails if any \
+revision of the commit has been changed, renamed, or removed, or if a new revision has \
+an id that does not come after all of them."
 )]
 struct Review {
     #[clap(flatten)]
@@ -97,6 +172,84 @@ struct Review {
 
     #[clap(flatten)]
     env: CliEnvironment,
+
+    #[clap(flatten)]
+    source: CliRevisionSource,
+
+    #[arg(
+        help = "Compare with the revisions of a git commit instead of the database, eg. `origin/main`",
+        long,
+        value_name = "REF",
+    )]
+    git_base: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Checks revisions for risky SQL, eg. statements locking existing tables",
+    long_about = "\
+Checks revisions for SQL that is risky to run against a database in use, eg. creating an index \
+without `CONCURRENTLY` or dropping a column. Lints the given files without connecting to a \
+database, or else all pending revisions. Each rule is a warning unless set to `error` or `off` \
+in the `[revisions.lint]` table of the config file, and can be skipped for a revision with a \
+`-- jrny:lint-ignore <rule>, ...` comment. Fails only if any findings are errors.",
+)]
+struct Lint {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(help = "The revision files to lint instead of the pending revisions")]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Checks revisions against the lockfile without a database",
+    long_about = "\
+Compares the revision files against the checksums recorded in `jrny.lock` next to the config \
+file, without connecting to a database, eg. so that CI can catch a revision being edited after \
+it was committed. Fails if any revision has changed or been removed since being locked, has a \
+duplicate id, or has been inserted before revisions that are already locked.",
+)]
+struct Check {
+    #[command(flatten)]
+    cfg: CliConfig,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Records the checksums of all revisions in the lockfile",
+    long_about = "\
+Writes the id, filename, and checksum of every revision to `jrny.lock` next to the config file \
+for `jrny check`, replacing its previous contents. Once the lockfile exists, `jrny plan` adds \
+each new revision to it, but it must be updated after editing a revision that is still pending.",
+)]
+struct Lock {
+    #[command(flatten)]
+    cfg: CliConfig,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Signs the checksums of all revisions with an ed25519 key",
+    long_about = "\
+Writes the id, filename, and checksum of every revision to `jrny.signed` next to the config \
+file, signed with the private key, replacing its previous contents. Environments that list the \
+key's public key under `signing.trusted_keys` only embark on revisions in a manifest it signed. \
+The key is read from a PKCS#8 PEM file, eg. as generated by `openssl genpkey -algorithm ed25519`.",
+)]
+struct Sign {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    source: CliRevisionSource,
+
+    #[arg(help = "The private key to sign with", long, value_name = "PATH")]
+    key: PathBuf,
 }
 
 #[derive(Parser, Debug)]
@@ -114,12 +267,311 @@ struct Embark {
     #[command(flatten)]
     env: CliEnvironment,
 
+    #[command(flatten)]
+    source: CliRevisionSource,
+
     #[arg(
         help = "The id of the last revision to run, defaulting to the latest revision",
         short,
         long,
     )]
-    through: Option<i32>,
+    through: Option<i64>,
+
+    #[arg(
+        help = "Record the revisions as applied without running them",
+        long,
+    )]
+    fake: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Reverts applied revisions by running their down files",
+    long_about = "\
+Reverts applied revisions, most recent first, by running the down file for each \
+(eg. `003.1618370298.add-users.down.sql` for `003.1618370298.add-users.sql`) and \
+removing the revision's record. Nothing is reverted if any of the targeted revisions \
+is missing a down file or if its down file has changed since the revision was applied.",
+)]
+struct Revert {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[command(flatten)]
+    source: CliRevisionSource,
+
+    #[arg(
+        help = "The id of the last revision to keep applied, reverting all after it",
+        long,
+        conflicts_with = "last",
+        required_unless_present = "last",
+    )]
+    to: Option<i64>,
+
+    #[arg(
+        help = "The number of most recently applied revisions to revert",
+        long,
+    )]
+    last: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Collapses revisions through a given id into a single baseline revision",
+    long_about = "\
+Creates a single baseline revision containing the schema as of the given revision, as dumped \
+by `pg_dump` from the target database, and moves the revisions it stands in for into the \
+`archive` subdirectory of the revisions directory. The database must have all revisions through \
+the given id applied and none after it.
+
+New databases will apply the baseline instead of the archived revisions, while databases that \
+already applied the archived revisions will treat the baseline as applied. Data inserted by the \
+archived revisions is not included and must be added to the baseline by hand if needed.",
+)]
+struct Baseline {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(
+        help = "The id of the last revision to collapse into the baseline",
+        short,
+        long,
+    )]
+    through: i64,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Renumbers pending revisions to follow those already applied",
+    long_about = "\
+Renumbers pending revisions so that they follow the last applied revision and each other, \
+keeping their relative order, eg. to resolve duplicate ids after merging branches that each \
+added revisions. Revision files (and their down files) are renamed, and any mention of the old \
+name in the comments at the start of the file is updated. Revisions already recorded as \
+applied are never renamed.",
+)]
+struct Renumber {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(
+        help = "Only report the revisions that would be renumbered, failing if there are any",
+        long,
+    )]
+    check: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Accepts the renaming of revision files after they were applied",
+    long_about = "\
+Updates the filenames (along with the names and timestamps within them) recorded for applied \
+revisions whose files have since been renamed. Renamed files are matched to their records by \
+id and checksum, so their contents must not have changed.",
+)]
+struct AcceptRenames {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Accepts an intentional change to an applied revision",
+    long_about = "\
+Updates the checksum recorded for an applied revision whose file has since been intentionally \
+changed, eg. to fix a comment, so that it no longer fails review. The change is shown first if \
+the applied contents can be found in the git history, and the previous checksum is recorded \
+in an audit table along with the reason for the change and who made it. This also accepts \
+a record that was changed outside of jrny, or that follows a removed record, by relinking it \
+into the chain of records.",
+)]
+struct Repair {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(help = "The id of the changed revision")]
+    id: i64,
+
+    #[arg(
+        help = "Why the revision was changed, which will otherwise be asked for at a terminal",
+        long,
+        short,
+    )]
+    reason: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Records pending revisions as applied without running them",
+    long_about = "\
+Records the pending revisions with the given ids as applied without running them, eg. when \
+adopting jrny for an existing database or after a revision was applied by hand. Review will \
+show that these revisions were never run by jrny.",
+)]
+struct MarkApplied {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(help = "The ids of the revisions to mark as applied", required = true)]
+    ids: Vec<i64>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Removes the record of an applied revision whose file no longer exists",
+    long_about = "\
+Removes the record of an applied revision whose file no longer exists, eg. one applied from \
+an abandoned branch, so that it no longer fails review. Optionally runs a SQL file within the \
+same transaction to clean up after the revision. If the environment is protected, this must be \
+confirmed with `--confirm`.",
+)]
+struct Forget {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(help = "The id or filename of the revision to forget")]
+    target: String,
+
+    #[arg(
+        help = "A SQL file to run in the same transaction, eg. to drop objects created by the revision",
+        long,
+    )]
+    run: Option<PathBuf>,
+
+    #[arg(help = "Confirm forgetting the revision in a protected environment", long)]
+    confirm: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Writes a snapshot of the schema to the configured file",
+    long_about = "\
+Writes a sorted text dump of the schema, built from the Postgres catalogs, to the file given \
+by `path` in the `[snapshot]` table of the config file, so that schema changes show up as \
+readable diffs in code review. All revisions must have been applied. The snapshot is also \
+written after every `embark` if configured.",
+)]
+struct Snapshot {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[arg(
+        help = "Fail if the existing snapshot does not match the schema instead of writing it",
+        long,
+    )]
+    check: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Applies all revisions to a new database to check that they still can be",
+    long_about = "\
+Creates a new database on the server of the environment's database, applies every revision \
+to it in order while reporting how long each took, and then drops the database. Fails on the \
+first revision that cannot be applied, eg. one that only worked against data or objects that \
+existed in older databases.",
+)]
+struct Verify {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+
+    #[command(flatten)]
+    source: CliRevisionSource,
+
+    #[arg(help = "Keep the new database afterwards, eg. to debug a failed revision", long)]
+    keep: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Writes all revisions to an archive to apply later",
+    long_about = "\
+Writes all revisions and their down files to a `.tar.gz` archive along with a manifest of \
+their checksums, eg. as a release artifact. Commands like `embark` can then apply exactly \
+these revisions with `--revisions-archive <path>`, which fails before running anything if \
+the archive does not match its manifest. Included files are expanded into the revisions.",
+)]
+struct Package {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    source: CliRevisionSource,
+
+    #[arg(help = "The archive to write, eg. `revisions-v1.4.0.tar.gz`")]
+    path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Upgrades the tables that track applied revisions",
+    long_about = "\
+Upgrades the tables that track applied revisions, which were created with INT revision ids by \
+older versions. Widening the ids rewrites the tables and locks them until done, so this is only \
+otherwise done by `embark`, never by commands that only read.",
+)]
+struct Upgrade {
+    #[command(flatten)]
+    cfg: CliConfig,
+
+    #[command(flatten)]
+    env: CliEnvironment,
+}
+
+#[derive(Parser, Debug)]
+struct CliRevisionSource {
+    #[arg(
+        help = "Read revisions as of a git commit, eg. a tag, instead of the working tree",
+        long,
+        value_name = "REF",
+        conflicts_with = "revisions_archive",
+    )]
+    revisions_ref: Option<String>,
+
+    #[arg(
+        help = "Read revisions from an archive written by `jrny package` instead of the working tree",
+        long,
+        value_name = "PATH",
+    )]
+    revisions_archive: Option<PathBuf>,
+}
+
+impl CliRevisionSource {
+    fn apply(self, mut cfg: Config) -> Config {
+        cfg.revisions.source = match (self.revisions_ref, self.revisions_archive) {
+            (Some(git_ref), _) => RevisionSource::GitRef(git_ref),
+            (_, Some(path)) => RevisionSource::Archive(path),
+            (None, None) => RevisionSource::WorkingTree,
+        };
+        cfg
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -182,12 +634,16 @@ impl CliEnvironment {
             },
         })?;
 
-        match self.db_url {
-            Some(url) => Ok(Environment::from_database_url(&url)),
-            None => match env_file {
-                Some(env) => Ok(env),
-                None => Err(JrnyError::EnvNotFound),
-            },
+        // The database url flag only overrides the url itself, so any other
+        // values from the env file (eg. variables) still apply.
+        match (self.db_url, env_file) {
+            (Some(url), Some(mut env)) => {
+                env.database.url = url;
+                Ok(env)
+            }
+            (Some(url), None) => Ok(Environment::from_database_url(&url)),
+            (None, Some(env)) => Ok(env),
+            (None, None) => Err(JrnyError::EnvNotFound),
         }
     }
 }
@@ -242,7 +698,22 @@ fn main() -> ExitCode {
         SubCommand::Begin(cmd) => begin(cmd),
         SubCommand::Plan(cmd) => plan(cmd),
         SubCommand::Review(cmd) => review(cmd),
+        SubCommand::Lint(cmd) => lint(cmd),
+        SubCommand::Check(cmd) => check(cmd),
+        SubCommand::Lock(cmd) => lock(cmd),
+        SubCommand::Sign(cmd) => sign(cmd),
         SubCommand::Embark(cmd) => embark(cmd),
+        SubCommand::Revert(cmd) => revert(cmd),
+        SubCommand::Baseline(cmd) => baseline(cmd),
+        SubCommand::Renumber(cmd) => renumber(cmd),
+        SubCommand::AcceptRenames(cmd) => accept_renames(cmd),
+        SubCommand::Repair(cmd) => repair(cmd),
+        SubCommand::MarkApplied(cmd) => mark_applied(cmd),
+        SubCommand::Forget(cmd) => forget(cmd),
+        SubCommand::Snapshot(cmd) => snapshot(cmd),
+        SubCommand::Verify(cmd) => verify(cmd),
+        SubCommand::Package(cmd) => package(cmd),
+        SubCommand::Upgrade(cmd) => upgrade(cmd),
     };
 
     // Returning the result directly would debugs print the error and exit with an
@@ -270,20 +741,166 @@ fn begin(cmd: Begin) -> JrnyResult<()> {
 fn plan(cmd: Plan) -> JrnyResult<()> {
     let cfg: Config = cmd.cfg.try_into()?;
 
-    // TODO: Allow passing in file contents via command-line?
-    jrny::plan(&cfg, &cmd.name, None)
+    let contents = match (cmd.from_file, cmd.stdin, cmd.from, cmd.to) {
+        (Some(path), _, _, _) => Some(fs::read_to_string(path)?),
+        (None, true, _, _) => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            Some(contents)
+        }
+        (None, false, Some(from), Some(to)) => match jrny::diff(&cfg, &cmd.name, &from, &to)? {
+            Some(contents) => Some(contents),
+            None => {
+                info!("No differences found between the schemas");
+                return Ok(());
+            }
+        },
+        _ => None,
+    };
+
+    let path = jrny::plan(
+        &cfg,
+        &cmd.name,
+        contents.as_deref(),
+        cmd.template.as_deref(),
+        cmd.edit,
+    )?;
+
+    // The bare path on its own line, so that scripts can use it
+    if let Some(path) = path {
+        println!("{}", path.display());
+    }
+
+    Ok(())
 }
 
 fn review(cmd: Review) -> JrnyResult<()> {
-    let cfg: Config = cmd.cfg.try_into()?;
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+
+    if let Some(git_ref) = cmd.git_base {
+        return jrny::review_against(&cfg, &git_ref);
+    }
+
     let env = cmd.env.jrny_environment(&cfg)?;
 
     jrny::review(&cfg, &env)
 }
 
+fn lint(cmd: Lint) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+
+    // Files given directly are linted without needing a database
+    let env = match cmd.paths.is_empty() {
+        true => Some(cmd.env.jrny_environment(&cfg)?),
+        false => None,
+    };
+
+    jrny::lint(&cfg, env.as_ref(), &cmd.paths)
+}
+
+fn check(cmd: Check) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+
+    jrny::check(&cfg)
+}
+
+fn lock(cmd: Lock) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+
+    jrny::lock(&cfg)
+}
+
+fn sign(cmd: Sign) -> JrnyResult<()> {
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+
+    jrny::sign(&cfg, &cmd.key)
+}
+
 fn embark(cmd: Embark) -> JrnyResult<()> {
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::embark(&cfg, &env, cmd.through, cmd.fake)
+}
+
+fn revert(cmd: Revert) -> JrnyResult<()> {
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    let target = match (cmd.to, cmd.last) {
+        (Some(id), _) => RevertTarget::To(id),
+        (None, Some(count)) => RevertTarget::Last(count),
+        (None, None) => unreachable!("clap requires either `--to` or `--last`"),
+    };
+
+    jrny::revert(&cfg, &env, target)
+}
+
+fn baseline(cmd: Baseline) -> JrnyResult<()> {
     let cfg: Config = cmd.cfg.try_into()?;
     let env = cmd.env.jrny_environment(&cfg)?;
 
-    jrny::embark(&cfg, &env, cmd.through)
+    jrny::baseline(&cfg, &env, cmd.through)
+}
+
+fn renumber(cmd: Renumber) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::renumber(&cfg, &env, cmd.check)
+}
+
+fn accept_renames(cmd: AcceptRenames) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::accept_renames(&cfg, &env)
+}
+
+fn repair(cmd: Repair) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::repair(&cfg, &env, cmd.id, cmd.reason.as_deref())
+}
+
+fn mark_applied(cmd: MarkApplied) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::mark_applied(&cfg, &env, &cmd.ids)
+}
+
+fn forget(cmd: Forget) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::forget(&cfg, &env, &cmd.target, cmd.run.as_deref(), cmd.confirm)
+}
+
+fn snapshot(cmd: Snapshot) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::snapshot(&cfg, &env, cmd.check)
+}
+
+fn package(cmd: Package) -> JrnyResult<()> {
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+
+    jrny::package(&cfg, &cmd.path)
+}
+
+fn upgrade(cmd: Upgrade) -> JrnyResult<()> {
+    let cfg: Config = cmd.cfg.try_into()?;
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::upgrade(&cfg, &env)
+}
+
+fn verify(cmd: Verify) -> JrnyResult<()> {
+    let cfg = cmd.source.apply(cmd.cfg.try_into()?);
+    let env = cmd.env.jrny_environment(&cfg)?;
+
+    jrny::verify(&cfg, &env, cmd.keep)
 }
@@ -1,33 +1,135 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, TimeZone, Utc};
+use flate2::read::GzDecoder;
 use sha2::{Digest, Sha256};
 
+use crate::archive::ArchiveTree;
+use crate::context::{ChecksumAlgorithm, FilenameScheme, RevisionsSettings};
+use crate::git::{self, GitTree};
+use crate::sql::{self, TokenKind};
 use crate::{Error, Result};
 
+/// Where revision files, and the files they include, are read from.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum RevisionSource {
+    /// The revisions directory on disk
+    #[default]
+    WorkingTree,
+    /// The revisions directory as of a git commit, eg. a branch or tag, read from
+    /// the repository containing the directory on disk
+    GitRef(String),
+    /// An archive written by `jrny package`, standing in for the revisions directory
+    Archive(PathBuf),
+}
+
+/// Reads files from the revision source.
+enum Reader {
+    WorkingTree,
+    Git(GitTree),
+    Archive(ArchiveTree),
+}
+
+impl Reader {
+    fn new(settings: &RevisionsSettings) -> Result<Self> {
+        match &settings.source {
+            RevisionSource::WorkingTree => Ok(Self::WorkingTree),
+            RevisionSource::GitRef(git_ref) => {
+                Ok(Self::Git(GitTree::open(&settings.directory, git_ref)?))
+            }
+            RevisionSource::Archive(path) => {
+                Ok(Self::Archive(ArchiveTree::open(path, &settings.directory)?))
+            }
+        }
+    }
+
+    /// The paths of the files directly within the directory, ignoring subdirectories.
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        match self {
+            Self::WorkingTree => fs::read_dir(dir)?
+                .map(|res| res.map(|e| e.path()).map_err(Error::IoError))
+                .filter(|res| res.as_ref().map_or(true, |p| !p.is_dir()))
+                .collect(),
+            Self::Git(tree) => tree.files(),
+            Self::Archive(archive) => Ok(archive.files()),
+        }
+    }
+
+    fn is_file(&self, p: &Path) -> Result<bool> {
+        match self {
+            Self::WorkingTree => Ok(p.is_file()),
+            Self::Git(tree) => tree.has_file(p),
+            Self::Archive(archive) => Ok(archive.read(p).is_some()),
+        }
+    }
+
+    /// Reads the file to a string, decompressing it first if its extension indicates
+    /// that it is compressed.
+    fn read_to_string(&self, p: &Path) -> Result<String> {
+        let not_found = || Error::FileNotFound(p.display().to_string());
+
+        match self {
+            Self::WorkingTree => decode_contents(p, fs::File::open(p)?),
+            Self::Git(tree) => decode_contents(p, tree.read(p)?.ok_or_else(not_found)?.as_slice()),
+            // Archives hold contents as checksummed, ie. decompressed with includes expanded
+            Self::Archive(archive) => archive.read(p).map(str::to_owned).ok_or_else(not_found),
+        }
+    }
+
+    /// Identifies the file regardless of the path taken to it, eg. to find include cycles.
+    fn canonical(&self, p: &Path) -> Result<PathBuf> {
+        match self {
+            Self::WorkingTree => Ok(p.canonicalize()?),
+            Self::Git(_) | Self::Archive(_) => Ok(git::normalize(p)),
+        }
+    }
+}
+
 /// The components that make up a revision title, excluding extension
 #[derive(Debug, PartialEq)]
 struct RevisionTitle {
     /// The numeric id extracted from the filename
-    id: i32,
+    id: i64,
     /// The file creation moment extracted from the filename
     created_at: DateTime<Utc>,
     /// The remaining portion of the filename, excluding extension
     name: String,
 }
 
+impl RevisionTitle {
+    /// Parses the filename using the given scheme, falling back to the original
+    /// `[id].[unix-timestamp].[name].sql` format.
+    fn parse(filename: &str, scheme: &FilenameScheme) -> Result<Self> {
+        let parsed = split_extension(filename).and_then(|(stem, _)| scheme.parse(stem));
+
+        match parsed {
+            Some((id, created_at, name)) => Ok(Self {
+                id,
+                created_at,
+                name,
+            }),
+            None => Self::try_from(filename),
+        }
+    }
+}
+
 impl TryFrom<&str> for RevisionTitle {
     type Error = crate::Error;
 
     fn try_from(filename: &str) -> std::result::Result<Self, Self::Error> {
-        let parts: Vec<&str> = filename.split('.').collect();
+        let (stem, _) = split_extension(filename)
+            .ok_or_else(|| Error::RevisionNameInvalid(filename.to_string()))?;
+
+        let parts: Vec<&str> = stem.split('.').collect();
 
-        if parts.len() < 4 || parts.last() != Some(&"sql") {
+        if parts.len() < 3 {
             return Err(Error::RevisionNameInvalid(filename.to_string()));
         }
 
-        let id: i32 = parts[0]
+        let id: i64 = parts[0]
             .parse()
             .map_err(|_| Error::RevisionNameInvalid(filename.to_string()))?;
 
@@ -41,7 +143,7 @@ impl TryFrom<&str> for RevisionTitle {
             .single()
             .ok_or_else(|| Error::RevisionTimestampOutOfRange(filename.to_string()))?;
 
-        let name = parts[2..parts.len() - 1].join(".");
+        let name = parts[2..].join(".");
 
         Ok(Self {
             id,
@@ -55,9 +157,11 @@ impl TryFrom<&str> for RevisionTitle {
 #[derive(Debug)]
 pub struct RevisionFile {
     /// The file id of the revision
-    pub id: i32,
+    pub id: i64,
     /// The hash of the contents
     pub checksum: String,
+    /// The algorithm used to compute the checksum
+    pub checksum_algorithm: ChecksumAlgorithm,
     /// Contents of revision file
     pub contents: String,
     /// Moment the revision was created
@@ -68,66 +172,551 @@ pub struct RevisionFile {
     pub name: String,
     /// Full path to the revision file
     pub path: PathBuf,
+    /// The optional file that reverts the revision
+    pub down: Option<RevisionDownFile>,
+    /// The revisions that this revision stands in for, if it is a baseline
+    pub baselined: Vec<BaselinedRevision>,
 }
 
 impl RevisionFile {
-    /// Attempts to read revision directory to convert all entries (assumed to be SQL files)
-    /// into metadata objects with contents stored.
-    pub fn all(revisions: &Path) -> Result<Vec<Self>> {
-        let mut entries = fs::read_dir(revisions)?
-            .map(|res| res.map(|e| e.path()).map_err(Error::IoError))
-            .collect::<Result<Vec<_>>>()?;
+    /// Attempts to read revision directory to convert all file entries (assumed to be SQL files)
+    /// into metadata objects with contents stored. Down files are attached to the revision
+    /// they revert rather than returned separately, and subdirectories are ignored so that
+    /// they can hold files shared between revisions.
+    pub fn all(settings: &RevisionsSettings) -> Result<Vec<Self>> {
+        let reader = Reader::new(settings)?;
+        let mut entries = reader.files(&settings.directory)?;
 
         entries.sort();
-        entries.iter().map(Self::try_from).collect()
+
+        let (downs, ups): (Vec<PathBuf>, Vec<PathBuf>) = entries.into_iter().partition(|p| {
+            p.file_name()
+                .and_then(|os_str| os_str.to_str())
+                .is_some_and(is_down_filename)
+        });
+
+        // Down files are matched by stem, since either file could be compressed
+        let mut downs: HashMap<String, PathBuf> = downs
+            .into_iter()
+            .map(|p| (stem_of(&p).to_string(), p))
+            .collect();
+
+        let revisions = ups
+            .iter()
+            .map(|p| {
+                let mut revision = Self::load_from(&reader, p, settings)?;
+
+                if let Some(down_path) = downs.remove(&format!("{}{}", stem_of(p), DOWN_SUFFIX)) {
+                    revision.down = Some(RevisionDownFile::load(
+                        &reader,
+                        &down_path,
+                        settings.checksum,
+                    )?);
+                }
+
+                Ok(revision)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Any down files left over do not have a revision to revert
+        if let Some(orphan) = downs.into_values().min() {
+            return Err(Error::RevisionDownOrphaned(orphan.display().to_string()));
+        }
+
+        Ok(revisions)
     }
-}
 
-impl TryFrom<&PathBuf> for RevisionFile {
-    type Error = crate::Error;
+    /// Attempts to gather appropriate metadata for and read contents of given path.
+    pub fn load(p: &Path, settings: &RevisionsSettings) -> Result<Self> {
+        Self::load_from(&Reader::new(settings)?, p, settings)
+    }
 
-    /// Attempts to gather appropriate metadata for and read contents of given path buf.
-    fn try_from(p: &PathBuf) -> std::result::Result<Self, Self::Error> {
+    fn load_from(reader: &Reader, p: &Path, settings: &RevisionsSettings) -> Result<Self> {
+        let checksum_algorithm = settings.checksum;
         let filename = p
             .file_name()
             .and_then(|os_str| os_str.to_str())
             .ok_or_else(|| Error::FileNotValid(p.display().to_string()))?;
 
-        let title = RevisionTitle::try_from(filename)?;
-        let contents = fs::read_to_string(p)?;
+        let title = RevisionTitle::parse(filename, &settings.filenames)?;
+        let contents = read_expanded(reader, p)?;
+        let baselined = contents
+            .lines()
+            .filter_map(|line| line.strip_prefix(BASELINE_DIRECTIVE))
+            .map(|entry| BaselinedRevision::parse(entry, filename))
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(Self {
             id: title.id,
-            checksum: to_checksum(&contents),
+            checksum: checksum_algorithm.checksum(&contents),
+            checksum_algorithm,
             contents,
             created_at: title.created_at,
             filename: filename.to_string(),
             name: title.name,
             path: p.to_owned(),
+            down: None,
+            baselined,
+        })
+    }
+
+    /// The hash of the contents as computed by the given algorithm, which can differ
+    /// from the configured algorithm for revisions applied before it changed.
+    pub fn checksum_with(&self, algorithm: ChecksumAlgorithm) -> String {
+        if algorithm == self.checksum_algorithm {
+            self.checksum.clone()
+        } else {
+            algorithm.checksum(&self.contents)
+        }
+    }
+
+    /// Returns the contents with all `{{ name }}` placeholders replaced by their
+    /// values, failing if any placeholder does not have a value.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String> {
+        render(&self.contents, &self.filename, variables)
+    }
+
+    /// The path at which the down file reverting this revision is expected to be,
+    /// eg. `001.1618370298.add-users.down.sql` for `001.1618370298.add-users.sql`,
+    /// though the down file can use any of the supported extensions.
+    pub fn down_path(&self) -> PathBuf {
+        let (stem, extension) = split_extension(&self.filename).unwrap_or((&self.filename, ""));
+
+        self.path
+            .with_file_name(format!("{}{}{}", stem, DOWN_SUFFIX, extension))
+    }
+
+    /// The down file reverting the applied revision, failing if there is none or if
+    /// it has changed since the revision was applied.
+    pub(crate) fn down_for(&self, record: &RevisionRecord) -> Result<&RevisionDownFile> {
+        let down = self
+            .down
+            .as_ref()
+            .ok_or_else(|| Error::RevisionDownNotFound(self.down_path().display().to_string()))?;
+
+        let down_checksum = record.checksum_algorithm.checksum(&down.contents);
+
+        if record.down_checksum.as_ref() != Some(&down_checksum) {
+            return Err(Error::RevisionDownChanged(down.filename.clone()));
+        }
+
+        Ok(down)
+    }
+}
+
+/// Metadata and contents for the optional file that reverts a revision.
+#[derive(Debug)]
+pub struct RevisionDownFile {
+    /// The hash of the contents
+    pub checksum: String,
+    /// Contents of the down file
+    pub contents: String,
+    /// The full name of the file, including id, timestamp, and extension
+    pub filename: String,
+    /// Full path to the down file
+    pub path: PathBuf,
+}
+
+impl RevisionDownFile {
+    /// Attempts to read contents of given path.
+    fn load(reader: &Reader, p: &Path, checksum_algorithm: ChecksumAlgorithm) -> Result<Self> {
+        let filename = p
+            .file_name()
+            .and_then(|os_str| os_str.to_str())
+            .ok_or_else(|| Error::FileNotValid(p.display().to_string()))?;
+
+        let contents = read_expanded(reader, p)?;
+
+        Ok(Self {
+            checksum: checksum_algorithm.checksum(&contents),
+            contents,
+            filename: filename.to_string(),
+            path: p.to_owned(),
         })
     }
+
+    /// Returns the contents with all `{{ name }}` placeholders replaced by their
+    /// values, failing if any placeholder does not have a value.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String> {
+        render(&self.contents, &self.filename, variables)
+    }
+}
+
+/// The prefix of each line in a baseline revision that names a revision it stands in for,
+/// eg. `-- jrny:baseline 001.1618370298.add-users.sql sha256 <checksum>`.
+pub(crate) const BASELINE_DIRECTIVE: &str = "-- jrny:baseline ";
+
+/// A revision that was collapsed into a baseline revision, as recorded at the time.
+#[derive(Debug)]
+pub struct BaselinedRevision {
+    /// The full name of the original file, including id, timestamp, and extension
+    pub filename: String,
+    /// The algorithm used to compute the checksum
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// The checksum recorded when the original revision was applied
+    pub checksum: String,
+}
+
+impl BaselinedRevision {
+    fn parse(entry: &str, filename: &str) -> Result<Self> {
+        let invalid = || Error::RevisionBaselineInvalid(entry.to_string(), filename.to_string());
+        let parts: Vec<&str> = entry.split_whitespace().collect();
+
+        match parts.as_slice() {
+            [baselined, algorithm, checksum] => Ok(Self {
+                filename: baselined.to_string(),
+                checksum_algorithm: algorithm.parse().map_err(|_| invalid())?,
+                checksum: checksum.to_string(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The line recording this revision in a baseline revision.
+    pub(crate) fn to_directive(&self) -> String {
+        format!(
+            "{}{} {} {}",
+            BASELINE_DIRECTIVE, self.filename, self.checksum_algorithm, self.checksum
+        )
+    }
 }
 
 /// Metadata stored for a revision that has already been applied.
 #[derive(Debug)]
 pub struct RevisionRecord {
     /// The database id of the revision
-    pub id: i32,
+    pub id: i64,
     /// Moment the revision was applied to the database
     pub applied_on: DateTime<Utc>,
     /// The hash of the contents
     pub checksum: String,
+    /// The algorithm used to compute the checksum (and down checksum)
+    pub checksum_algorithm: ChecksumAlgorithm,
     /// Moment the revision was created
     pub created_at: DateTime<Utc>,
     /// The full name of the file, including timestamp and extension
     pub filename: String,
     /// The name of the file, excluding timestamp and extension
     pub name: String,
+    /// The hash of the down file contents, if one was present when applied
+    pub down_checksum: Option<String>,
+    /// Whether the revision was recorded as applied without being run
+    pub faked: bool,
+    /// The hash of the record and the chain hash of the record before it, if any
+    pub chain_hash: Option<String>,
+}
+
+impl RevisionRecord {
+    /// Computes the chain hash of the record following the record with the given
+    /// chain hash, from everything recorded for the revision. Strings are quoted
+    /// so that no value can be mistaken for the separators.
+    pub(crate) fn chain_hash_after(&self, previous: Option<&str>) -> String {
+        let data = format!(
+            "{:?} {} {} {} {:?} {:?} {} {} {:?} {}",
+            previous.unwrap_or(""),
+            self.id,
+            self.created_at.timestamp_micros(),
+            self.applied_on.timestamp_micros(),
+            self.filename,
+            self.name,
+            self.checksum_algorithm,
+            self.checksum,
+            self.down_checksum.as_deref().unwrap_or(""),
+            self.faked,
+        );
+
+        format!("{:x}", Sha256::digest(data.as_bytes()))
+    }
+}
+
+/// The ids of the records, ordered by id, whose chain hash does not follow from the
+/// record before them - ie. records that were changed or inserted without updating
+/// the chain, or that follow a record that was removed.
+pub(crate) fn broken_links(records: &[RevisionRecord]) -> HashSet<i64> {
+    let mut previous: Option<&str> = None;
+    let mut broken = HashSet::new();
+
+    for record in records {
+        if record.chain_hash.as_deref() != Some(&record.chain_hash_after(previous)) {
+            broken.insert(record.id);
+        }
+        previous = record.chain_hash.as_deref();
+    }
+
+    broken
+}
+
+/// An intentional change to an applied revision, accepted by updating its checksum.
+#[derive(Debug)]
+pub struct RevisionRepair {
+    /// The database id of the repaired revision
+    pub revision_id: i64,
+    /// The full name of the repaired revision's file
+    pub filename: String,
+    /// Why the revision was changed
+    pub reason: String,
+    /// Who repaired the revision
+    pub repaired_by: String,
+    /// Moment the revision was repaired
+    pub repaired_on: DateTime<Utc>,
+}
+
+/// The supported extensions for revision files, which can optionally be compressed
+const EXTENSIONS: &[&str] = &[".sql", ".sql.gz", ".sql.zst"];
+
+/// The suffix of the stem distinguishing down files from the revisions they revert
+const DOWN_SUFFIX: &str = ".down";
+
+/// Splits the filename into its stem and extension, if the extension is supported.
+fn split_extension(filename: &str) -> Option<(&str, &str)> {
+    EXTENSIONS
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext).map(|stem| (stem, *ext)))
 }
 
-fn to_checksum(s: &str) -> String {
-    // See: https://users.rust-lang.org/t/sha256-result-to-string/49391/3
-    format!("{:x}", Sha256::digest(s.as_bytes()))
+/// The filename excluding any supported extension.
+pub(crate) fn filename_stem(filename: &str) -> &str {
+    split_extension(filename).map_or(filename, |(stem, _)| stem)
+}
+
+/// The filename of the path excluding any supported extension.
+fn stem_of(p: &Path) -> &str {
+    filename_stem(
+        p.file_name()
+            .and_then(|os_str| os_str.to_str())
+            .unwrap_or(""),
+    )
+}
+
+fn is_down_filename(filename: &str) -> bool {
+    split_extension(filename).is_some_and(|(stem, _)| stem.ends_with(DOWN_SUFFIX))
+}
+
+/// Reads the contents of the file at the given path from the reader, decompressing
+/// them first if the extension of the path indicates that they are compressed.
+pub(crate) fn decode_contents(p: &Path, mut reader: impl Read) -> Result<String> {
+    let mut contents = String::new();
+
+    match p.extension().and_then(|os_str| os_str.to_str()) {
+        Some("gz") => {
+            GzDecoder::new(reader).read_to_string(&mut contents)?;
+        }
+        Some("zst") => {
+            zstd::Decoder::new(reader)?.read_to_string(&mut contents)?;
+        }
+        _ => {
+            reader.read_to_string(&mut contents)?;
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Reads the file at the given path, replacing each include directive with the contents
+/// of the file it names. The directive must be on its own line, either as
+/// `-- jrny:include path/to/file.sql` or psql-style `\i path/to/file.sql` (or `\ir`),
+/// and in all cases the path is relative to the file containing the directive.
+fn read_expanded(reader: &Reader, p: &Path) -> Result<String> {
+    expand_includes(reader, p, &mut Vec::new())
+}
+
+fn expand_includes(reader: &Reader, p: &Path, including: &mut Vec<PathBuf>) -> Result<String> {
+    let canonical = reader.canonical(p)?;
+
+    if including.contains(&canonical) {
+        let cycle: Vec<String> = including
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+
+        return Err(Error::RevisionIncludeCycle(cycle.join(" -> ")));
+    }
+
+    let contents = reader.read_to_string(p)?;
+
+    // Most files do not include any others, so avoid rebuilding their contents
+    if !contents
+        .lines()
+        .any(|line| include_directive(line).is_some())
+    {
+        return Ok(contents);
+    }
+
+    including.push(canonical);
+
+    let mut expanded = String::with_capacity(contents.len());
+
+    for line in contents.split_inclusive('\n') {
+        match include_directive(line) {
+            Some(include) => {
+                let include_path = p.parent().unwrap_or(Path::new("")).join(include);
+
+                if !reader.is_file(&include_path)? {
+                    return Err(Error::RevisionIncludeNotFound(
+                        include_path.display().to_string(),
+                        p.display().to_string(),
+                    ));
+                }
+
+                let included = expand_includes(reader, &include_path, including)?;
+
+                expanded.push_str(&included);
+
+                if !included.ends_with('\n') {
+                    expanded.push('\n');
+                }
+            }
+            None => expanded.push_str(line),
+        }
+    }
+
+    including.pop();
+
+    Ok(expanded)
+}
+
+/// Returns the path named by the line if it is an include directive.
+fn include_directive(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let path = line
+        .strip_prefix("-- jrny:include ")
+        .or_else(|| line.strip_prefix("\\ir "))
+        .or_else(|| line.strip_prefix("\\i "))?
+        .trim();
+
+    let path = path
+        .strip_prefix('\'')
+        .and_then(|p| p.strip_suffix('\''))
+        .unwrap_or(path);
+
+    (!path.is_empty()).then_some(path)
+}
+
+/// Substitutes the variables into the `{{ name }}` placeholders of the SQL from the named
+/// file, failing if any placeholder has no value.
+pub(crate) fn render(
+    contents: &str,
+    filename: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String> {
+    let mut missing = None;
+    let rendered = replace_sql_placeholders(contents, |name| {
+        let value = variables.get(name).cloned();
+
+        if value.is_none() && missing.is_none() {
+            missing = Some(name.to_string());
+        }
+        value
+    });
+
+    match missing {
+        Some(name) => Err(Error::VariableNotDefined(name, filename.to_string())),
+        None => Ok(rendered),
+    }
+}
+
+/// Replaces each `{{ name }}` placeholder in the text with the value returned for the
+/// name, leaving the placeholder untouched if there is no value. Names must start with
+/// a letter or underscore, followed by any letters, digits, underscores, or periods;
+/// anything else between braces (eg. a nested array literal) is not a placeholder.
+pub(crate) fn replace_placeholders<F>(text: &str, mut value_of: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let placeholder = rest.find("}}").map(|end| (&rest[2..end], end + 2));
+
+        match placeholder {
+            Some((inner, len)) if is_placeholder_name(inner.trim()) => {
+                match value_of(inner.trim()) {
+                    Some(value) => output.push_str(&value),
+                    None => output.push_str(&rest[..len]),
+                }
+                rest = &rest[len..];
+            }
+            _ => {
+                output.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Replaces placeholders as `replace_placeholders` does, except for any within string
+/// literals (including dollar-quoted bodies) or comments, where text such as
+/// `'{{x}}'::text[]` is valid SQL rather than a placeholder.
+fn replace_sql_placeholders<F>(sql: &str, mut value_of: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(sql.len());
+    let mut code_start = 0;
+
+    for token in sql::tokenize(sql) {
+        if matches!(token.kind, TokenKind::String | TokenKind::Comment) {
+            output.push_str(&replace_placeholders(
+                &sql[code_start..token.offset],
+                &mut value_of,
+            ));
+            output.push_str(token.text);
+            code_start = token.offset + token.text.len();
+        }
+    }
+
+    output.push_str(&replace_placeholders(&sql[code_start..], &mut value_of));
+    output
+}
+
+fn is_placeholder_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// A revision file with the given contents that is not read from disk, for tests.
+#[cfg(test)]
+pub(crate) fn revision_file(id: i64, name: &str, contents: &str) -> RevisionFile {
+    let algorithm = ChecksumAlgorithm::default();
+
+    RevisionFile {
+        id,
+        checksum: algorithm.checksum(contents),
+        checksum_algorithm: algorithm,
+        contents: contents.to_string(),
+        created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+        filename: format!("{:03}.1577836800.{}.sql", id, name),
+        name: name.to_string(),
+        path: PathBuf::new(),
+        down: None,
+        baselined: Vec::new(),
+    }
+}
+
+/// The default settings for revisions in the given directory, for tests.
+#[cfg(test)]
+pub(crate) fn test_settings(dir: &Path) -> RevisionsSettings {
+    RevisionsSettings {
+        directory: dir.to_owned(),
+        checksum: ChecksumAlgorithm::default(),
+        filenames: FilenameScheme::default(),
+        ids: Default::default(),
+        lint: Default::default(),
+        templates: HashMap::new(),
+        source: RevisionSource::WorkingTree,
+    }
 }
 
 #[cfg(test)]
@@ -140,7 +729,7 @@ mod tests {
             RevisionTitle::try_from("001.1577836800.some-file.sql").unwrap(),
             RevisionTitle {
                 id: 1,
-                created_at: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+                created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
                 name: "some-file".to_string(),
             }
         )
@@ -152,12 +741,53 @@ mod tests {
             RevisionTitle::try_from("003.1577836800.some.file.sql").unwrap(),
             RevisionTitle {
                 id: 3,
-                created_at: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+                created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
                 name: "some.file".to_string(),
             }
         )
     }
 
+    #[test]
+    fn revision_title_allows_compressed_sql() {
+        for filename in [
+            "002.1577836800.some.file.sql.gz",
+            "002.1577836800.some.file.sql.zst",
+        ] {
+            assert_eq!(
+                RevisionTitle::try_from(filename).unwrap(),
+                RevisionTitle {
+                    id: 2,
+                    created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+                    name: "some.file".to_string(),
+                }
+            )
+        }
+    }
+
+    #[test]
+    fn revision_title_parses_configured_or_original_format() {
+        let scheme = FilenameScheme {
+            timestamp: crate::context::TimestampFormat::Datetime,
+            separator: "_".to_string(),
+            ..Default::default()
+        };
+        let expected = |id| RevisionTitle {
+            id,
+            created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            name: "some_file".to_string(),
+        };
+
+        assert_eq!(
+            RevisionTitle::parse("004_20200101000000_some_file.sql", &scheme).unwrap(),
+            expected(4)
+        );
+        assert_eq!(
+            RevisionTitle::parse("003.1577836800.some_file.sql", &scheme).unwrap(),
+            expected(3)
+        );
+        assert!(RevisionTitle::parse("005_1577836800_some_file.sql", &scheme).is_err());
+    }
+
     #[test]
     fn revision_title_fails_non_sql() {
         match RevisionTitle::try_from("001.1577836800.some-file.wat") {
@@ -183,4 +813,259 @@ mod tests {
             result => panic!("received {:?}", result),
         }
     }
+
+    #[test]
+    fn replace_placeholders_substitutes_names() {
+        let text = "grant usage on schema {{ schema }} to {{role}};";
+        let replaced = replace_placeholders(text, |name| match name {
+            "schema" => Some("app".to_string()),
+            "role" => Some("app_owner_staging".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(replaced, "grant usage on schema app to app_owner_staging;");
+    }
+
+    #[test]
+    fn replace_placeholders_ignores_non_names() {
+        let text = "select '{{1,2},{3,4}}'::int[], '{{ a b }}', '{{ missing }}', '{{';";
+
+        assert_eq!(replace_placeholders(text, |_| None), text);
+    }
+
+    #[test]
+    fn render_skips_strings_and_comments() {
+        let variables = HashMap::from([("owner".to_string(), "app".to_string())]);
+        let contents = "\
+-- Grants to {{ owner }}
+select '{{x}}'::text[], $$ {{ y }} $$;
+grant usage on schema app to {{ owner }};";
+
+        assert_eq!(
+            render(contents, "001.1577836800.some-file.sql", &variables).unwrap(),
+            "\
+-- Grants to {{ owner }}
+select '{{x}}'::text[], $$ {{ y }} $$;
+grant usage on schema app to app;"
+        );
+    }
+
+    #[test]
+    fn render_fails_missing_variable() {
+        let variables = HashMap::from([("schema".to_string(), "app".to_string())]);
+
+        match render(
+            "{{ schema }}.{{ table }}",
+            "001.1577836800.some-file.sql",
+            &variables,
+        ) {
+            Err(Error::VariableNotDefined(name, filename)) => {
+                assert_eq!(name, "table");
+                assert_eq!(filename, "001.1577836800.some-file.sql");
+            }
+            result => panic!("received {:?}", result),
+        }
+    }
+
+    #[test]
+    fn include_directive_parses_paths() {
+        assert_eq!(
+            include_directive("-- jrny:include shared/fn.sql\n"),
+            Some("shared/fn.sql")
+        );
+        assert_eq!(
+            include_directive("  \\i 'shared/fn.sql'"),
+            Some("shared/fn.sql")
+        );
+        assert_eq!(
+            include_directive("\\ir shared/fn.sql"),
+            Some("shared/fn.sql")
+        );
+        assert_eq!(include_directive("-- jrny:include"), None);
+        assert_eq!(include_directive("-- include shared/fn.sql"), None);
+        assert_eq!(include_directive("select '\\i shared/fn.sql';"), None);
+    }
+
+    #[test]
+    fn read_expanded_fails_include_cycle() {
+        let dir = std::env::temp_dir().join(format!("jrny-include-cycle-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.sql"), "select 1;\n-- jrny:include b.sql\n").unwrap();
+        fs::write(dir.join("b.sql"), "\\i a.sql\n").unwrap();
+
+        let result = read_expanded(&Reader::WorkingTree, &dir.join("a.sql"));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        match result {
+            Err(Error::RevisionIncludeCycle(cycle)) => {
+                assert!(cycle.ends_with("a.sql"), "received {}", cycle);
+            }
+            result => panic!("received {:?}", result),
+        }
+    }
+
+    #[test]
+    fn all_pairs_down_files_with_revisions() {
+        let dir = std::env::temp_dir().join(format!("jrny-down-pairs-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("001.1577836800.users.sql"),
+            "create table users ();",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("001.1577836800.users.down.sql"),
+            "drop table users;",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("002.1577836800.posts.sql"),
+            "create table posts ();",
+        )
+        .unwrap();
+
+        let result = RevisionFile::all(&test_settings(&dir));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let revisions = result.unwrap();
+        let down = revisions[0].down.as_ref().unwrap();
+
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(down.filename, "001.1577836800.users.down.sql");
+        assert_eq!(down.contents, "drop table users;");
+        assert!(revisions[1].down.is_none());
+    }
+
+    #[test]
+    fn all_fails_orphaned_down_file() {
+        let dir = std::env::temp_dir().join(format!("jrny-down-orphan-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("001.1577836800.users.sql"),
+            "create table users ();",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("002.1577836800.posts.down.sql"),
+            "drop table posts;",
+        )
+        .unwrap();
+
+        let result = RevisionFile::all(&test_settings(&dir));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        match result {
+            Err(Error::RevisionDownOrphaned(path)) => {
+                assert!(
+                    path.ends_with("002.1577836800.posts.down.sql"),
+                    "received {}",
+                    path
+                );
+            }
+            result => panic!("received {:?}", result),
+        }
+    }
+
+    #[test]
+    fn down_for_fails_missing_or_changed_down_file() {
+        let dir = std::env::temp_dir().join(format!("jrny-down-changed-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("001.1577836800.users.sql"),
+            "create table users ();",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("001.1577836800.users.down.sql"),
+            "drop table users;",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("002.1577836800.posts.sql"),
+            "create table posts ();",
+        )
+        .unwrap();
+
+        let result = RevisionFile::all(&test_settings(&dir));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let revisions = result.unwrap();
+        let record = |file: &RevisionFile, down_checksum: Option<&str>| RevisionRecord {
+            id: file.id,
+            applied_on: file.created_at,
+            checksum: file.checksum.clone(),
+            checksum_algorithm: file.checksum_algorithm,
+            created_at: file.created_at,
+            filename: file.filename.clone(),
+            name: file.name.clone(),
+            down_checksum: down_checksum.map(str::to_string),
+            faked: false,
+            chain_hash: None,
+        };
+
+        let applied = ChecksumAlgorithm::default().checksum("drop table users;");
+        let users = &revisions[0];
+
+        assert!(users.down_for(&record(users, Some(&applied))).is_ok());
+        assert!(matches!(
+            users.down_for(&record(users, Some("something-else"))),
+            Err(Error::RevisionDownChanged(filename)) if filename == "001.1577836800.users.down.sql"
+        ));
+        assert!(matches!(
+            revisions[1].down_for(&record(&revisions[1], None)),
+            Err(Error::RevisionDownNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn broken_links_finds_records_changed_removed_or_inserted() {
+        let record = |id: i64| RevisionRecord {
+            id,
+            applied_on: Utc.with_ymd_and_hms(2020, 1, 2, 0, 0, 0).unwrap(),
+            checksum: format!("checksum-{}", id),
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            created_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            filename: format!("{:03}.1577836800.r.sql", id),
+            name: "r".to_string(),
+            down_checksum: None,
+            faked: false,
+            chain_hash: None,
+        };
+        let chained = |ids: &[i64]| {
+            let mut previous = None;
+
+            ids.iter()
+                .map(|id| {
+                    let mut record = record(*id);
+                    let hash = record.chain_hash_after(previous.as_deref());
+
+                    record.chain_hash = Some(hash.clone());
+                    previous = Some(hash);
+                    record
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(broken_links(&chained(&[1, 2, 3, 4])).is_empty());
+
+        let mut changed = chained(&[1, 2, 3, 4]);
+        changed[1].checksum = "checksum-other".to_string();
+        assert_eq!(broken_links(&changed), HashSet::from([2]));
+
+        let mut removed = chained(&[1, 2, 3, 4]);
+        removed.remove(1);
+        assert_eq!(broken_links(&removed), HashSet::from([3]));
+
+        let mut inserted = chained(&[1, 2, 4]);
+        inserted.insert(2, record(3));
+        assert_eq!(broken_links(&inserted), HashSet::from([3, 4]));
+    }
 }
@@ -0,0 +1,317 @@
+//! Manifests of revision checksums signed with an ed25519 key, so that environments
+//! can require that every revision applied was approved by the holder of a trusted key.
+//!
+//! Checksums are always of the exact SQL, ignoring the configured normalizations, since
+//! those would allow changes within string literals and function bodies. Variables are
+//! substituted after the signature is checked, so their values are not covered.
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::context::ChecksumAlgorithm;
+use crate::revisions::{filename_stem, RevisionFile};
+use crate::{Error, Result};
+
+const HEADER: &str = "\
+# Checksums of the revisions signed by `jrny sign` - do not edit by hand, since any change
+# invalidates the signature.
+";
+
+/// Prefixed to the signed message so that the signature cannot be reused for anything else
+const CONTEXT: &str = "jrny signed manifest v1\n";
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedManifest {
+    /// The hex-encoded public key of the signer
+    pub key: String,
+    /// The hex-encoded signature of the revisions
+    pub signature: String,
+    #[serde(default, rename = "revision")]
+    pub revisions: Vec<SignedRevision>,
+}
+
+/// A revision as signed in the manifest.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedRevision {
+    pub id: i64,
+    /// The full name of the file, including id, timestamp, and extension
+    pub filename: String,
+    /// The SHA-256 hash of the contents, without any normalization
+    pub checksum: String,
+    /// The SHA-256 hash of the contents of the down file, if the revision has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_checksum: Option<String>,
+}
+
+impl From<&RevisionFile> for SignedRevision {
+    fn from(file: &RevisionFile) -> Self {
+        Self {
+            id: file.id,
+            filename: file.filename.clone(),
+            checksum: exact_checksum(&file.contents),
+            down_checksum: file
+                .down
+                .as_ref()
+                .map(|down| exact_checksum(&down.contents)),
+        }
+    }
+}
+
+impl SignedManifest {
+    /// Signs the id, filename, and checksums of each of the revisions and its down file.
+    pub fn sign(files: &[RevisionFile], key: &SigningKey) -> Self {
+        let mut revisions: Vec<SignedRevision> = files.iter().map(SignedRevision::from).collect();
+
+        revisions.sort_by(|a, b| (a.id, &a.filename).cmp(&(b.id, &b.filename)));
+
+        let signature = key.sign(message(&revisions).as_bytes());
+
+        Self {
+            key: encode(key.verifying_key().as_bytes()),
+            signature: encode(&signature.to_bytes()),
+            revisions,
+        }
+    }
+
+    /// Reads the signed manifest at the path, if it exists, without verifying it.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let manifest: Self = toml::from_str(&contents)
+            .map_err(|e| Error::TomlInvalid(e, path.display().to_string()))?;
+
+        Ok(Some(manifest))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let body = toml::to_string(self).expect("signed manifest should serialize");
+
+        fs::write(path, format!("{}\n{}", HEADER, body))?;
+
+        Ok(())
+    }
+
+    /// Whether the manifest was signed by one of the trusted keys and has not been
+    /// changed since.
+    pub fn verify(&self, trusted: &[VerifyingKey]) -> bool {
+        let Some(signature) =
+            decode(&self.signature).and_then(|bytes| Signature::from_slice(&bytes).ok())
+        else {
+            return false;
+        };
+        let message = message(&self.revisions);
+
+        trusted
+            .iter()
+            .filter(|key| encode(key.as_bytes()) == self.key)
+            .any(|key| key.verify(message.as_bytes(), &signature).is_ok())
+    }
+
+    /// Whether the revision and its down file, if any, are in the manifest with exactly
+    /// the same contents.
+    pub fn covers(&self, file: &RevisionFile) -> bool {
+        let stem = filename_stem(&file.filename);
+
+        self.revisions
+            .iter()
+            .find(|revision| filename_stem(&revision.filename) == stem)
+            .is_some_and(|revision| {
+                let current = SignedRevision::from(file);
+
+                revision.checksum == current.checksum
+                    && revision.down_checksum == current.down_checksum
+            })
+    }
+}
+
+/// The signed message, with one line per revision. Filenames are quoted so that
+/// no filename can be mistaken for the separators.
+fn message(revisions: &[SignedRevision]) -> String {
+    let mut message = CONTEXT.to_string();
+
+    for revision in revisions {
+        writeln!(
+            message,
+            "{} {:?} {} {}",
+            revision.id,
+            revision.filename,
+            revision.checksum,
+            revision.down_checksum.as_deref().unwrap_or("-")
+        )
+        .expect("writing to a string should not fail");
+    }
+
+    message
+}
+
+/// The SHA-256 hash of the contents exactly as they are.
+fn exact_checksum(contents: &str) -> String {
+    ChecksumAlgorithm::default().checksum(contents)
+}
+
+/// Reads the private key from a PKCS#8 PEM file, eg. as generated by
+/// `openssl genpkey -algorithm ed25519`.
+pub fn read_signing_key(path: &Path) -> Result<SigningKey> {
+    let pathstr = path.display().to_string();
+
+    if !path.is_file() {
+        return Err(Error::FileNotFound(pathstr));
+    }
+
+    SigningKey::from_pkcs8_pem(&fs::read_to_string(path)?)
+        .map_err(|e| Error::SigningKeyInvalid(pathstr, e.to_string()))
+}
+
+/// Parses a hex-encoded public key, as reported by `jrny sign`.
+pub fn parse_verifying_key(hex: &str) -> Result<VerifyingKey> {
+    let invalid = |reason: &str| Error::SigningKeyInvalid(hex.to_string(), reason.to_string());
+
+    let bytes: [u8; 32] = decode(hex)
+        .ok_or_else(|| invalid("expected a hex-encoded key"))?
+        .try_into()
+        .map_err(|_| invalid("expected a 32-byte key"))?;
+
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid("not a point on the curve"))
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::context::ChecksumAlgorithm;
+    use crate::revisions::{revision_file, RevisionDownFile};
+
+    #[test]
+    fn signed_manifest_verifies_only_with_trusted_keys_and_unchanged_revisions() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let trusted = [key.verifying_key()];
+
+        let manifest = SignedManifest::sign(
+            &[
+                revision_file(1, "a", "select 1;"),
+                revision_file(2, "b", "select 2;"),
+            ],
+            &key,
+        );
+        let manifest: SignedManifest =
+            toml::from_str(&toml::to_string(&manifest).unwrap()).unwrap();
+
+        assert!(manifest.verify(&trusted));
+        assert!(!manifest.verify(&[other.verifying_key()]));
+        assert!(manifest.covers(&revision_file(2, "b", "select 2;")));
+        assert!(!manifest.covers(&revision_file(2, "b", "select 'two';")));
+        assert!(!manifest.covers(&revision_file(3, "c", "select 3;")));
+
+        let mut tampered = manifest;
+        tampered.revisions[1].checksum = ChecksumAlgorithm::default().checksum("select 'two';");
+
+        assert!(!tampered.verify(&trusted));
+
+        // Re-signing with an untrusted key cannot pass for the trusted one
+        let mut forged =
+            SignedManifest::sign(&[revision_file(1, "a", "drop table users;")], &other);
+        forged.key = encode(key.verifying_key().as_bytes());
+
+        assert!(!forged.verify(&trusted));
+    }
+
+    fn with_down(mut file: RevisionFile, contents: &str) -> RevisionFile {
+        file.down = Some(RevisionDownFile {
+            checksum: file.checksum_algorithm.checksum(contents),
+            contents: contents.to_string(),
+            filename: file.filename.replace(".sql", ".down.sql"),
+            path: PathBuf::new(),
+        });
+        file
+    }
+
+    #[test]
+    fn signed_manifest_covers_down_files() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let trusted = [key.verifying_key()];
+        let file = || with_down(revision_file(1, "a", "create table a ();"), "drop table a;");
+
+        let manifest = SignedManifest::sign(&[file()], &key);
+
+        assert!(manifest.verify(&trusted));
+        assert!(manifest.covers(&file()));
+        assert!(!manifest.covers(&with_down(
+            revision_file(1, "a", "create table a ();"),
+            "select 1;"
+        )));
+        assert!(!manifest.covers(&revision_file(1, "a", "create table a ();")));
+
+        let mut tampered = manifest;
+        tampered.revisions[0].down_checksum =
+            Some(ChecksumAlgorithm::default().checksum("select 1;"));
+
+        assert!(!tampered.verify(&trusted));
+    }
+
+    #[test]
+    fn signed_manifest_ignores_checksum_normalization() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let algorithm = ChecksumAlgorithm {
+            line_endings: true,
+            ..Default::default()
+        };
+        let file = |contents: &str| {
+            let mut file = revision_file(1, "a", contents);
+
+            file.checksum_algorithm = algorithm;
+            file.checksum = algorithm.checksum(contents);
+            file
+        };
+
+        let manifest = SignedManifest::sign(&[file("select 'a\r\nb';")], &key);
+
+        // The same once normalized, but not the same SQL
+        assert_eq!(
+            file("select 'a\nb';").checksum,
+            file("select 'a\r\nb';").checksum
+        );
+        assert!(manifest.covers(&file("select 'a\r\nb';")));
+        assert!(!manifest.covers(&file("select 'a\nb';")));
+    }
+
+    #[test]
+    fn verifying_keys_parse_from_hex() {
+        let key = SigningKey::from_bytes(&[7; 32]).verifying_key();
+
+        assert_eq!(parse_verifying_key(&encode(key.as_bytes())).unwrap(), key);
+        assert!(matches!(
+            parse_verifying_key("abc"),
+            Err(Error::SigningKeyInvalid(_, _))
+        ));
+        assert!(matches!(
+            parse_verifying_key("abcd"),
+            Err(Error::SigningKeyInvalid(_, _))
+        ));
+    }
+}
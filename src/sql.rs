@@ -0,0 +1,289 @@
+//! A minimal SQL lexer, just capable enough to tell comments and quoted text
+//! apart from the statements around them.
+
+/// The kinds of tokens distinguished by the lexer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A `-- line comment` (excluding the newline) or `/* block comment */`
+    Comment,
+    /// A `'string'`, `E'escape string'`, or `$tag$dollar-quoted string$tag$`
+    String,
+    /// A `"quoted identifier"`
+    QuotedIdentifier,
+    /// An unquoted keyword, identifier, number, or parameter
+    Word,
+    /// Any run of whitespace
+    Whitespace,
+    /// Any other single character, eg. `;` or `(`
+    Symbol,
+}
+
+/// A slice of SQL text along with its kind and byte offset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub offset: usize,
+}
+
+/// Splits the SQL text into tokens that, concatenated, reproduce the text exactly.
+/// Unterminated comments and quotes extend to the end of the text.
+pub(crate) fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+
+    while offset < sql.len() {
+        let rest = &sql[offset..];
+        let (kind, len) = next_token(rest);
+
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+            offset,
+        });
+        offset += len;
+    }
+
+    tokens
+}
+
+/// Returns the SQL text with all comments removed.
+pub(crate) fn strip_comments(sql: &str) -> String {
+    tokenize(sql)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .map(|token| token.text)
+        .collect()
+}
+
+/// Commands that control transactions when starting a statement.
+const TRANSACTION_COMMANDS: &[&str] = &["abort", "begin", "commit", "end", "rollback", "start"];
+
+/// Returns the first transaction command (eg. `COMMIT`) starting a statement, if any,
+/// ignoring any within quotes or comments and the `BEGIN ATOMIC ... END` body of
+/// a function, which do not control the transaction.
+pub(crate) fn transaction_command(sql: &str) -> Option<&str> {
+    let mut previous: Option<&str> = None;
+    let mut atomic_depth = 0;
+
+    for token in tokenize(sql) {
+        match token.kind {
+            TokenKind::Word => {
+                let word = token.text;
+                let starts_statement = previous.is_none();
+
+                if word.eq_ignore_ascii_case("atomic")
+                    && previous.is_some_and(|p| p.eq_ignore_ascii_case("begin"))
+                {
+                    atomic_depth += 1;
+                } else if word.eq_ignore_ascii_case("end")
+                    && atomic_depth > 0
+                    && (starts_statement
+                        || previous.is_some_and(|p| p.eq_ignore_ascii_case("atomic")))
+                {
+                    atomic_depth -= 1;
+                } else if starts_statement
+                    && atomic_depth == 0
+                    && TRANSACTION_COMMANDS
+                        .iter()
+                        .any(|cmd| word.eq_ignore_ascii_case(cmd))
+                {
+                    return Some(word);
+                }
+
+                previous = Some(word);
+            }
+            TokenKind::Symbol if token.text == ";" => previous = None,
+            TokenKind::Comment | TokenKind::Whitespace => {}
+            _ => previous = Some(token.text),
+        }
+    }
+
+    None
+}
+
+fn next_token(rest: &str) -> (TokenKind, usize) {
+    use TokenKind::*;
+
+    let first = rest.chars().next().unwrap();
+
+    if rest.starts_with("--") {
+        return (Comment, rest.find('\n').unwrap_or(rest.len()));
+    }
+    if rest.starts_with("/*") {
+        return (Comment, block_comment_len(rest));
+    }
+    if rest.starts_with("E'") || rest.starts_with("e'") {
+        return (String, 1 + quoted_len(&rest[1..], '\'', true));
+    }
+    if first == '\'' {
+        return (String, quoted_len(rest, '\'', false));
+    }
+    if first == '"' {
+        return (QuotedIdentifier, quoted_len(rest, '"', false));
+    }
+    if first == '$' {
+        if let Some(len) = dollar_quoted_len(rest) {
+            return (String, len);
+        }
+    }
+    if first.is_whitespace() {
+        return (Whitespace, len_while(rest, char::is_whitespace));
+    }
+    if is_word_char(first) {
+        return (Word, len_while(rest, is_word_char));
+    }
+
+    (Symbol, first.len_utf8())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn len_while(rest: &str, pred: impl Fn(char) -> bool) -> usize {
+    rest.find(|c| !pred(c)).unwrap_or(rest.len())
+}
+
+/// Length of a block comment, which can be nested.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+
+    rest.len()
+}
+
+/// Length of text quoted by the given character, where the quote is escaped by
+/// doubling it or (if allowed) by a preceding backslash.
+fn quoted_len(rest: &str, quote: char, backslash_escapes: bool) -> usize {
+    let mut chars = rest.char_indices().skip(1).peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if backslash_escapes && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            match chars.peek() {
+                Some((_, next)) if *next == quote => {
+                    chars.next();
+                }
+                _ => return i + c.len_utf8(),
+            }
+        }
+    }
+
+    rest.len()
+}
+
+/// Length of dollar-quoted text, if the text starts with a valid `$tag$` opening.
+fn dollar_quoted_len(rest: &str) -> Option<usize> {
+    let tag_len = rest[1..].find('$')? + 2;
+    let tag = &rest[..tag_len];
+    let name = &tag[1..tag_len - 1];
+
+    let valid_name = name.is_empty()
+        || (name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_'));
+
+    if !valid_name {
+        return None;
+    }
+
+    Some(
+        rest[tag_len..]
+            .find(tag)
+            .map_or(rest.len(), |end| tag_len + end + tag_len),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_distinguishes_comments_and_quotes() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("select '-- no', \"a\"\"b\" -- yes\n/* a /* b */ c */$1;"),
+            vec![
+                (Word, "select"),
+                (Whitespace, " "),
+                (String, "'-- no'"),
+                (Symbol, ","),
+                (Whitespace, " "),
+                (QuotedIdentifier, "\"a\"\"b\""),
+                (Whitespace, " "),
+                (Comment, "-- yes"),
+                (Whitespace, "\n"),
+                (Comment, "/* a /* b */ c */"),
+                (Word, "$1"),
+                (Symbol, ";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_handles_escape_and_dollar_strings() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("E'it\\'s' $fn$ -- not a comment $fn$ $$x$$"),
+            vec![
+                (String, "E'it\\'s'"),
+                (Whitespace, " "),
+                (String, "$fn$ -- not a comment $fn$"),
+                (Whitespace, " "),
+                (String, "$$x$$"),
+            ]
+        );
+    }
+
+    #[test]
+    fn transaction_command_finds_commands_starting_statements() {
+        assert_eq!(
+            transaction_command("delete from t; -- begin\nCOMMIT;"),
+            Some("COMMIT")
+        );
+        assert_eq!(
+            transaction_command("do $$ begin perform 1; end $$; select 'commit';"),
+            None
+        );
+        assert_eq!(
+            transaction_command(
+                "create function f() returns int begin atomic select 1; end; drop table t;"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_comments_keeps_everything_else() {
+        assert_eq!(
+            strip_comments("-- header\nselect 1; /* trailing */\n"),
+            "\nselect 1; \n"
+        );
+    }
+}